@@ -0,0 +1,429 @@
+//! Command-line controller for Brio Smart Tech locomotives.
+
+mod shell;
+mod sweep;
+mod tui;
+
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use brio_smart_tech::{
+    BrioSmartTech, BrioSmartTechBuilder, Color, Command, Direction, Motion,
+    Notification, ReconnectPolicy, SoundTheme, Speed, TrainHandle, discover,
+};
+use btleplug::{
+    api::{BDAddr, Manager as _},
+    platform::{Adapter, Manager},
+};
+use clap::{Args, Parser, Subcommand};
+use env_logger::Env;
+use serde::Serialize;
+use tokio::{
+    signal::ctrl_c,
+    sync::broadcast::error::RecvError,
+    time::{Duration, sleep},
+};
+
+#[derive(Parser)]
+#[command(version, about = "Control Brio Smart Tech locomotives")]
+struct Cli {
+    /// Bluetooth address of the locomotive to control.
+    #[arg(long, global = true, conflicts_with = "name")]
+    address: Option<BDAddr>,
+
+    /// Advertised name of the locomotive to control.
+    #[arg(long, global = true)]
+    name: Option<String>,
+
+    /// Seconds spent looking for locomotives.
+    #[arg(
+        long,
+        global = true,
+        default_value = "10",
+        value_parser = parse_seconds,
+    )]
+    timeout: Duration,
+
+    /// Print JSON instead of text.
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Subcommand)]
+enum Action {
+    /// List the locomotives nearby, strongest signal first.
+    Scan,
+    /// Drive at a speed from -7 (full speed backward) to 7.
+    Speed {
+        #[arg(
+            allow_negative_numbers = true,
+            value_parser = clap::value_parser!(i8).range(-7..=7),
+        )]
+        speed: i8,
+        #[command(flatten)]
+        hold: Hold,
+    },
+    /// Drive forward at a speed from 1 to 7.
+    Forward {
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=7))]
+        speed: u8,
+        #[command(flatten)]
+        hold: Hold,
+    },
+    /// Drive backward at a speed from 1 to 7.
+    Backward {
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=7))]
+        speed: u8,
+        #[command(flatten)]
+        hold: Hold,
+    },
+    /// Stop the locomotive.
+    Stop,
+    /// Set the light color, such as `white` or `light-blue`.
+    Color {
+        color: Color,
+        /// From 0 to 15.
+        #[arg(
+            default_value_t = 15,
+            value_parser = clap::value_parser!(u8).range(0..=15),
+        )]
+        intensity: u8,
+    },
+    /// Select the set of sounds: honk, whistle, horn or spaceship.
+    SoundTheme { theme: SoundTheme },
+    /// Print the notifications sent by the locomotive until interrupted.
+    Monitor,
+    /// Control the locomotive interactively.
+    Shell,
+    /// Drive the locomotive from a dashboard, using the keyboard.
+    Tui,
+    /// Send ranges of payloads and report how the locomotive reacts.
+    ///
+    /// Each byte of the pattern is given in hexadecimal, either fixed or as
+    /// an inclusive range: `56 aa 00-ff` sends every sound byte in turn.
+    Sweep {
+        #[arg(required = true)]
+        pattern: Vec<String>,
+        /// Seconds spent collecting the notifications after each payload.
+        #[arg(long, default_value = "0.5", value_parser = parse_seconds)]
+        wait: Duration,
+        /// Seconds to wait for the locomotive to come back after a
+        /// disconnection, before giving up.
+        #[arg(long, default_value = "30", value_parser = parse_seconds)]
+        recover: Duration,
+        /// Write the JSON report to this file.
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+}
+
+/// The locomotive stops once the controller exits, so driving lasts until
+/// interrupted or for the given time.
+#[derive(Args)]
+struct Hold {
+    /// Seconds to keep driving before stopping.
+    #[arg(long, value_parser = parse_seconds)]
+    duration: Option<Duration>,
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("{value:?} is not a number of seconds"))
+}
+
+#[derive(Serialize)]
+struct TrainInfo {
+    address: String,
+    name: String,
+    rssi: Option<i16>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    // The dashboard owns the terminal, diagnostics would garble it.
+    if !matches!(cli.action, Action::Tui) {
+        env_logger::Builder::from_env(Env::default().default_filter_or("warn"))
+            .init();
+    }
+
+    match run(&cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            if cli.json {
+                let error = serde_json::json!({ "error": err.to_string() });
+                eprintln!("{error}");
+            } else {
+                eprintln!("Error: {err}");
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let manager = Manager::new().await?;
+    let central = manager
+        .adapters()
+        .await?
+        .into_iter()
+        .next()
+        .ok_or("no Bluetooth adapter found")?;
+
+    if let Action::Scan = cli.action {
+        return scan(cli, &central).await;
+    }
+
+    if let Action::Sweep {
+        ref pattern,
+        wait,
+        recover,
+        ref report,
+    } = cli.action
+    {
+        let report = report.as_deref();
+        return sweep(cli, &central, pattern, wait, recover, report).await;
+    }
+
+    let train = connect(cli, &central).await?;
+    match cli.action {
+        Action::Scan | Action::Sweep { .. } => {
+            unreachable!("handled separately")
+        }
+        Action::Speed { speed, ref hold } => {
+            let direction = if speed < 0 {
+                Direction::Backward
+            } else {
+                Direction::Forward
+            };
+            let speed = Speed::new(speed.unsigned_abs())?;
+            drive(cli, &train, Motion::new(direction, speed), hold).await?;
+        }
+        Action::Forward { speed, ref hold } => {
+            let motion = Motion::new(Direction::Forward, Speed::new(speed)?);
+            drive(cli, &train, motion, hold).await?;
+        }
+        Action::Backward { speed, ref hold } => {
+            let motion = Motion::new(Direction::Backward, Speed::new(speed)?);
+            drive(cli, &train, motion, hold).await?;
+        }
+        Action::Stop => {
+            send(cli, &train, Command::SetSpeed(Motion::default())).await?;
+        }
+        Action::Color { color, intensity } => {
+            send(cli, &train, Command::SetColor { color, intensity }).await?;
+        }
+        Action::SoundTheme { theme } => {
+            send(cli, &train, Command::SetSoundTheme(theme)).await?;
+        }
+        Action::Monitor => monitor(cli, &train).await?,
+        Action::Shell => shell::run(&train).await?,
+        Action::Tui => tui::run(&train).await?,
+    }
+
+    train.disconnect(false).await?;
+    Ok(())
+}
+
+async fn scan(cli: &Cli, central: &Adapter) -> Result<(), Box<dyn Error>> {
+    let trains: Vec<TrainInfo> = discover(central, cli.timeout)
+        .await?
+        .into_iter()
+        .map(|train| TrainInfo {
+            address: train.address.to_string(),
+            name: train.name,
+            rssi: train.rssi,
+        })
+        .collect();
+
+    for line in scan_lines(cli.json, &trains)? {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+fn scan_lines(
+    json: bool,
+    trains: &[TrainInfo],
+) -> serde_json::Result<Vec<String>> {
+    if json {
+        return Ok(vec![serde_json::to_string(trains)?]);
+    }
+    Ok(trains
+        .iter()
+        .map(|train| {
+            let rssi = train
+                .rssi
+                .map_or_else(|| "?".to_string(), |rssi| format!("{rssi} dBm"));
+            format!("{}  {}  {rssi}", train.address, train.name)
+        })
+        .collect())
+}
+
+async fn connect(
+    cli: &Cli,
+    central: &Adapter,
+) -> Result<TrainHandle, Box<dyn Error>> {
+    connect_with(cli, central, BrioSmartTech::builder()).await
+}
+
+async fn connect_with(
+    cli: &Cli,
+    central: &Adapter,
+    builder: BrioSmartTechBuilder,
+) -> Result<TrainHandle, Box<dyn Error>> {
+    let mut builder = builder.scan_timeout(cli.timeout);
+    if let Some(address) = cli.address {
+        builder = builder.address(address);
+    }
+    if let Some(name) = &cli.name {
+        builder = builder.name(name);
+    }
+    Ok(builder.connect(central).await?)
+}
+
+async fn sweep(
+    cli: &Cli,
+    central: &Adapter,
+    words: &[String],
+    wait: Duration,
+    recover: Duration,
+    report_path: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let pattern = sweep::Pattern::parse(words)?;
+    // Payloads may crash the locomotive, keep going once it is back.
+    let builder =
+        BrioSmartTech::builder().reconnect(ReconnectPolicy::default());
+    let train = connect_with(cli, central, builder).await?;
+
+    eprintln!("Sending {} payloads", pattern.count());
+    let report =
+        sweep::sweep(&train, &pattern, words, wait, recover, |probe| {
+            eprintln!("{}", sweep::describe(probe));
+        })
+        .await;
+
+    if let Some(path) = report_path {
+        fs::write(path, serde_json::to_string_pretty(&report)?)?;
+    }
+    if cli.json {
+        println!("{}", serde_json::to_string(&report)?);
+    } else {
+        println!("{}", report.summary());
+    }
+    train.disconnect(false).await?;
+    Ok(())
+}
+
+async fn send(
+    cli: &Cli,
+    train: &TrainHandle,
+    command: Command,
+) -> Result<(), Box<dyn Error>> {
+    train.send(command).await?;
+    println!("{}", sent_line(cli.json, &command));
+    Ok(())
+}
+
+fn sent_line(json: bool, command: &Command) -> String {
+    if json {
+        serde_json::json!({ "sent": command }).to_string()
+    } else {
+        format!("Sent {command:?}")
+    }
+}
+
+fn notification_line(
+    json: bool,
+    notification: &Notification,
+) -> serde_json::Result<String> {
+    if json {
+        serde_json::to_string(notification)
+    } else {
+        Ok(format!("{notification:?}"))
+    }
+}
+
+async fn drive(
+    cli: &Cli,
+    train: &TrainHandle,
+    motion: Motion,
+    hold: &Hold,
+) -> Result<(), Box<dyn Error>> {
+    send(cli, train, Command::SetSpeed(motion)).await?;
+    match hold.duration {
+        Some(duration) => tokio::select! {
+            () = sleep(duration) => {}
+            result = ctrl_c() => result?,
+        },
+        None => ctrl_c().await?,
+    }
+    Ok(())
+}
+
+async fn monitor(cli: &Cli, train: &TrainHandle) -> Result<(), Box<dyn Error>> {
+    let mut notifications = train.subscribe();
+
+    loop {
+        let notification = tokio::select! {
+            notification = notifications.recv() => notification,
+            result = ctrl_c() => return Ok(result?),
+        };
+        match notification {
+            Ok(notification) => {
+                println!("{}", notification_line(cli.json, &notification)?);
+            }
+            Err(RecvError::Lagged(count)) => {
+                eprintln!("Missed {count} notifications");
+            }
+            Err(RecvError::Closed) => return Err("connection closed".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use brio_smart_tech::{BrioSmartTech, sim::SimTransport};
+
+    use super::*;
+
+    /// Connecting goes through the library, which must keep stdout for the
+    /// JSON output, and every line printed must parse on its own.
+    #[tokio::test]
+    async fn test_json_output() {
+        let sim = SimTransport::new();
+        let train = BrioSmartTech::with_transport(sim.clone()).await.unwrap();
+        let mut notifications = train.subscribe();
+        let command = Command::SetColor {
+            color: Color::Red,
+            intensity: 8,
+        };
+        train.send(command).await.unwrap();
+        let notification = notifications.recv().await.unwrap();
+
+        let trains = [TrainInfo {
+            address: "01:02:03:04:05:06".to_string(),
+            name: "Smart 2.0 Train".to_string(),
+            rssi: None,
+        }];
+        let lines = [
+            scan_lines(true, &trains).unwrap(),
+            vec![sent_line(true, &command)],
+            vec![notification_line(true, &notification).unwrap()],
+        ];
+        for line in lines.iter().flatten() {
+            assert!(!line.contains('\n'), "{line}");
+            serde_json::from_str::<serde_json::Value>(line).unwrap();
+        }
+    }
+}
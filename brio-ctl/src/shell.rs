@@ -0,0 +1,328 @@
+//! Interactive shell for live control and protocol exploration.
+
+use std::{
+    error::Error, path::PathBuf, str::FromStr, sync::mpsc as std_mpsc, thread,
+};
+
+use brio_smart_tech::{
+    BrioError, Color, Command, Direction, EffectSlot, Motion, SoundEffect,
+    SoundTheme, Speed, TrainHandle,
+};
+use rustyline::{
+    Context, Editor, ExternalPrinter as _, Helper, Highlighter, Hinter,
+    Validator, completion::Completer, error::ReadlineError,
+    history::DefaultHistory,
+};
+use strum::IntoEnumIterator;
+use tokio::{
+    sync::{broadcast::error::RecvError, mpsc},
+    task,
+};
+
+const PROMPT: &str = "brio> ";
+
+const HELP: &str = "\
+forward|fwd <1-7>          drive forward
+backward|back <1-7>        drive backward
+speed <-7..7>              drive, backward when negative
+stop                       stop
+color <color> [0-15]       set the lights, at full intensity by default
+theme <theme>              select the set of sounds
+sound <theme> <1-4>        play a sound effect
+raw <byte>...              send a payload given in hexadecimal
+state                      show the last known state
+help                       show this help
+quit|exit                  leave the shell";
+
+/// Words the shell understands, offered for completion.
+const WORDS: &[&str] = &[
+    "forward", "fwd", "backward", "back", "speed", "stop", "color", "theme",
+    "sound", "raw", "state", "help", "quit", "exit",
+];
+
+/// What a line typed in the shell asks for.
+#[derive(Debug, PartialEq)]
+enum Line {
+    Empty,
+    Send(Command),
+    /// Payload sent as is, whether the library knows it or not.
+    Raw(Vec<u8>),
+    State,
+    Help,
+    Quit,
+}
+
+fn parse_line(line: &str) -> Result<Line, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((&word, args)) = words.split_first() else {
+        return Ok(Line::Empty);
+    };
+
+    let line = match (word, args) {
+        ("forward" | "fwd", [speed]) => {
+            drive(Direction::Forward, parse_speed(speed, 1)?)
+        }
+        ("backward" | "back", [speed]) => {
+            drive(Direction::Backward, parse_speed(speed, 1)?)
+        }
+        ("speed", [speed]) => match speed.strip_prefix('-') {
+            Some(speed) => drive(Direction::Backward, parse_speed(speed, 0)?),
+            None => drive(Direction::Forward, parse_speed(speed, 0)?),
+        },
+        ("stop", []) => Line::Send(Command::SetSpeed(Motion::default())),
+        ("color", [color, intensity @ ..]) if intensity.len() <= 1 => {
+            let intensity = match intensity.first() {
+                Some(intensity) => intensity
+                    .parse()
+                    .map_err(|_| format!("invalid intensity {intensity:?}"))?,
+                None => 15,
+            };
+            Line::Send(Command::SetColor {
+                color: parse(color)?,
+                intensity,
+            })
+        }
+        ("theme", [theme]) => Line::Send(Command::SetSoundTheme(parse(theme)?)),
+        ("sound", [theme, slot]) => {
+            let theme = parse(theme)?;
+            let slot = slot
+                .parse::<usize>()
+                .ok()
+                .and_then(|slot| EffectSlot::iter().nth(slot.checked_sub(1)?))
+                .ok_or_else(|| format!("invalid sound effect {slot:?}"))?;
+            Line::Send(Command::PlaySoundEffect(SoundEffect::new(theme, slot)))
+        }
+        ("raw", bytes) if !bytes.is_empty() => {
+            let payload = bytes
+                .iter()
+                .map(|byte| {
+                    let digits = byte.trim_start_matches("0x");
+                    u8::from_str_radix(digits, 16)
+                        .map_err(|_| format!("invalid byte {byte:?}"))
+                })
+                .collect::<Result<Vec<u8>, String>>()?;
+            Line::Raw(payload)
+        }
+        ("state", []) => Line::State,
+        ("help", []) => Line::Help,
+        ("quit" | "exit", []) => Line::Quit,
+        _ if WORDS.contains(&word) => {
+            return Err(format!("wrong arguments for {word}, see help"));
+        }
+        _ => return Err(format!("unknown command {word:?}, see help")),
+    };
+    Ok(line)
+}
+
+fn parse<T: FromStr<Err = BrioError>>(name: &str) -> Result<T, String> {
+    name.parse().map_err(|err: BrioError| err.to_string())
+}
+
+fn parse_speed(speed: &str, min: u8) -> Result<Speed, String> {
+    speed
+        .parse::<u8>()
+        .ok()
+        .filter(|speed| *speed >= min)
+        .and_then(|speed| Speed::new(speed).ok())
+        .ok_or_else(|| format!("speed {speed} is out of range {min}..=7"))
+}
+
+fn drive(direction: Direction, speed: Speed) -> Line {
+    Line::Send(Command::SetSpeed(Motion::new(direction, speed)))
+}
+
+/// Completion of the command words, colors and sound themes.
+#[derive(Helper, Highlighter, Hinter, Validator)]
+struct ShellHelper;
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind(' ').map_or(0, |index| index + 1);
+        let (done, prefix) = line.split_at(start);
+
+        let candidates: Vec<String> =
+            match done.split_whitespace().collect::<Vec<_>>()[..] {
+                [] => WORDS.iter().map(ToString::to_string).collect(),
+                ["color"] => Color::iter().map(|c| c.to_string()).collect(),
+                ["theme" | "sound"] => {
+                    SoundTheme::iter().map(|t| t.to_string()).collect()
+                }
+                _ => Vec::new(),
+            };
+        Ok((
+            start,
+            candidates
+                .into_iter()
+                .filter(|candidate| candidate.starts_with(prefix))
+                .collect(),
+        ))
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join(".brio-ctl-history"))
+}
+
+/// Read and run commands until the user leaves, printing the notifications
+/// as they come.
+pub async fn run(train: &TrainHandle) -> Result<(), Box<dyn Error>> {
+    let mut editor = Editor::<ShellHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(ShellHelper));
+    let history = history_path();
+    if let Some(path) = &history {
+        // There is no history on the first run.
+        let _ = editor.load_history(path);
+    }
+
+    let mut printer = editor.create_external_printer()?;
+    let mut notifications = train.subscribe();
+    let printing = task::spawn(async move {
+        loop {
+            let message = match notifications.recv().await {
+                Ok(notification) => format!("<- {notification:?}"),
+                Err(RecvError::Lagged(count)) => {
+                    format!("<- missed {count} notifications")
+                }
+                Err(RecvError::Closed) => return,
+            };
+            if printer.print(message).is_err() {
+                return;
+            }
+        }
+    });
+
+    // The editor blocks while reading, it gets a thread of its own. Each
+    // line is acknowledged once run, so that its output comes before the
+    // next prompt.
+    let (lines, mut line_receiver) = mpsc::channel(1);
+    let (done, done_receiver) = std_mpsc::channel();
+    let reader = thread::spawn(move || {
+        loop {
+            let line = editor.readline(PROMPT);
+            if let Ok(line) = &line {
+                let _ = editor.add_history_entry(line.as_str());
+            }
+            if lines.blocking_send(line).is_err()
+                || done_receiver.recv().is_err()
+            {
+                break;
+            }
+        }
+        if let Some(path) = &history
+            && let Err(err) = editor.save_history(path)
+        {
+            eprintln!("Could not save the history: {err}");
+        }
+    });
+
+    while let Some(line) = line_receiver.recv().await {
+        let line = match line {
+            Ok(line) => line,
+            // Ctrl-C only drops the line being typed.
+            Err(ReadlineError::Interrupted) => String::new(),
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        };
+        match parse_line(&line) {
+            Ok(Line::Empty) => {}
+            Ok(Line::Send(command)) => match train.send(command).await {
+                Ok(()) => println!("-> {command:?}"),
+                Err(err) => println!("Error: {err}"),
+            },
+            Ok(Line::Raw(payload)) => match train.send_raw(&payload).await {
+                Ok(()) => match Command::from_payload(&payload) {
+                    Ok(command) => println!("-> {payload:02x?} {command:?}"),
+                    Err(_) => println!("-> {payload:02x?}"),
+                },
+                Err(err) => println!("Error: {err}"),
+            },
+            Ok(Line::State) => println!("{:#?}", train.state()),
+            Ok(Line::Help) => println!("{HELP}"),
+            Ok(Line::Quit) => break,
+            Err(err) => println!("Error: {err}"),
+        }
+        if done.send(()).is_err() {
+            break;
+        }
+    }
+
+    drop(done);
+    printing.abort();
+    task::spawn_blocking(move || reader.join())
+        .await?
+        .map_err(|_| "the line editor panicked")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rustyline::history::DefaultHistory;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_line("  "), Ok(Line::Empty));
+        assert_eq!(
+            parse_line("fwd 5"),
+            Ok(drive(Direction::Forward, Speed::new(5).unwrap()))
+        );
+        assert_eq!(
+            parse_line("speed -2"),
+            Ok(drive(Direction::Backward, Speed::new(2).unwrap()))
+        );
+        assert_eq!(
+            parse_line("color blue 12"),
+            Ok(Line::Send(Command::SetColor {
+                color: Color::Blue,
+                intensity: 12
+            }))
+        );
+        assert_eq!(
+            parse_line("raw 56 aa 0xf2"),
+            Ok(Line::Raw(vec![0x56, 0xaa, 0xf2]))
+        );
+        assert_eq!(
+            parse_line("sound spaceship 4"),
+            Ok(Line::Send(Command::PlaySoundEffect(SoundEffect::new(
+                SoundTheme::Spaceship,
+                EffectSlot::Effect4
+            ))))
+        );
+
+        for line in
+            ["fwd 0", "fwd 8", "color", "sound horn 5", "raw 1g", "jump"]
+        {
+            assert!(parse_line(line).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn test_complete() {
+        let history = DefaultHistory::new();
+        let context = Context::new(&history);
+        let complete = |line: &str| {
+            ShellHelper.complete(line, line.len(), &context).unwrap()
+        };
+
+        assert_eq!(complete("ba"), (0, vec!["backward".into(), "back".into()]));
+        assert_eq!(
+            complete("color light"),
+            (6, vec!["light-blue".to_string()])
+        );
+        assert_eq!(
+            complete("theme h"),
+            (6, vec!["honk".into(), "horn".into()])
+        );
+        assert_eq!(complete("stop "), (5, Vec::<String>::new()));
+    }
+}
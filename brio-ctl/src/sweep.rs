@@ -0,0 +1,320 @@
+//! Sending ranges of payloads and recording how the locomotive reacts.
+
+use std::ops::RangeInclusive;
+
+use brio_smart_tech::{ConnectionState, Notification, TrainHandle};
+use serde::Serialize;
+use tokio::{
+    sync::broadcast::error::RecvError,
+    time::{Duration, Instant, timeout, timeout_at},
+};
+
+/// Most payloads a pattern may produce, more would take days to send.
+pub const MAX_PAYLOADS: usize = 1 << 16;
+
+/// Payloads to send, each byte taking every value of its range in turn.
+#[derive(Debug, PartialEq)]
+pub struct Pattern(Vec<RangeInclusive<u8>>);
+
+impl Pattern {
+    /// Parse bytes given in hexadecimal, either fixed (`56`) or as an
+    /// inclusive range (`00-ff`).
+    pub fn parse(words: &[String]) -> Result<Self, String> {
+        let byte = |word: &str| {
+            u8::from_str_radix(word.trim_start_matches("0x"), 16)
+                .map_err(|_| format!("invalid byte {word:?}"))
+        };
+        let ranges = words
+            .iter()
+            .map(|word| match word.split_once('-') {
+                Some((start, end)) => {
+                    let range = byte(start)?..=byte(end)?;
+                    if range.is_empty() {
+                        return Err(format!("empty range {word:?}"));
+                    }
+                    Ok(range)
+                }
+                None => byte(word).map(|byte| byte..=byte),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if ranges.is_empty() {
+            return Err("the pattern is empty".to_string());
+        }
+        match count(&ranges) {
+            Some(count) if count <= MAX_PAYLOADS => Ok(Self(ranges)),
+            _ => Err(format!("the pattern exceeds {MAX_PAYLOADS} payloads")),
+        }
+    }
+
+    /// Number of payloads.
+    pub fn count(&self) -> usize {
+        // Bounded when parsing.
+        count(&self.0).unwrap()
+    }
+
+    /// Every payload, the last byte varying first.
+    pub fn payloads(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        (0..self.count()).map(|mut index| {
+            let mut payload = vec![0; self.0.len()];
+            for (byte, range) in payload.iter_mut().zip(&self.0).rev() {
+                let count = range.clone().count();
+                *byte = range.start() + (index % count) as u8;
+                index /= count;
+            }
+            payload
+        })
+    }
+}
+
+fn count(ranges: &[RangeInclusive<u8>]) -> Option<usize> {
+    ranges
+        .iter()
+        .try_fold(1, |count: usize, range| count.checked_mul(range.len()))
+}
+
+/// How the locomotive reacted to a payload.
+#[derive(Debug, Serialize)]
+pub struct Probe {
+    /// Payload, in hexadecimal.
+    pub payload: String,
+    /// Whether the payload went out, see `error` otherwise.
+    pub sent: bool,
+    /// Notifications received while waiting for answers.
+    pub notifications: Vec<Notification>,
+    /// Why the payload could not be sent.
+    pub error: Option<String>,
+    /// The link dropped, the locomotive having possibly crashed.
+    pub disconnected: bool,
+}
+
+/// Outcome of a sweep.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub pattern: Vec<String>,
+    /// Time spent collecting the answers to each payload, in milliseconds.
+    pub wait_ms: u128,
+    pub probes: Vec<Probe>,
+    /// Whether every payload was sent, the sweep stopping when the
+    /// locomotive cannot be reconnected.
+    pub completed: bool,
+}
+
+impl Report {
+    pub fn summary(&self) -> String {
+        let answered = self
+            .probes
+            .iter()
+            .filter(|p| !p.notifications.is_empty())
+            .count();
+        let disconnected =
+            self.probes.iter().filter(|p| p.disconnected).count();
+        let sent = self.probes.iter().filter(|p| p.sent).count();
+        let mut summary = format!(
+            "{sent} sent, {answered} answered, {disconnected} disconnections"
+        );
+        if !self.completed {
+            summary += ", sweep aborted";
+        }
+        summary
+    }
+}
+
+fn hex(payload: &[u8]) -> String {
+    payload.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Send `payload` and collect the notifications received within `wait`.
+pub async fn probe(
+    train: &TrainHandle,
+    payload: &[u8],
+    wait: Duration,
+) -> Probe {
+    let mut notifications = train.subscribe();
+    let mut probe = Probe {
+        payload: hex(payload),
+        sent: false,
+        notifications: Vec::new(),
+        error: None,
+        disconnected: false,
+    };
+
+    match train.send_raw(payload).await {
+        Ok(()) => {
+            probe.sent = true;
+            let deadline = Instant::now() + wait;
+            loop {
+                match timeout_at(deadline, notifications.recv()).await {
+                    Ok(Ok(notification)) => {
+                        probe.notifications.push(notification);
+                    }
+                    Ok(Err(RecvError::Lagged(_))) => {}
+                    Ok(Err(RecvError::Closed)) | Err(_) => break,
+                }
+            }
+        }
+        Err(err) => probe.error = Some(err.to_string()),
+    }
+
+    probe.disconnected = *train.connection_state().borrow()
+        != ConnectionState::Ready
+        || !train.is_connected().await.unwrap_or(false);
+    probe
+}
+
+/// Wait up to `recover` for the link to be ready.
+async fn recovered(train: &TrainHandle, recover: Duration) -> bool {
+    let mut connection_state = train.connection_state();
+    let ready =
+        connection_state.wait_for(|state| *state == ConnectionState::Ready);
+    matches!(timeout(recover, ready).await, Ok(Ok(_)))
+}
+
+/// Send every payload of `pattern`, waiting up to `recover` for the link to
+/// come back after a disconnection.
+///
+/// A payload that could not be sent is sent again once the link is back,
+/// the sweep stopping if it fails twice. `progress` is called after each
+/// payload.
+pub async fn sweep(
+    train: &TrainHandle,
+    pattern: &Pattern,
+    words: &[String],
+    wait: Duration,
+    recover: Duration,
+    mut progress: impl FnMut(&Probe),
+) -> Report {
+    let mut report = Report {
+        pattern: words.to_vec(),
+        wait_ms: wait.as_millis(),
+        probes: Vec::new(),
+        completed: false,
+    };
+
+    for payload in pattern.payloads() {
+        let mut probe = self::probe(train, &payload, wait).await;
+        if !probe.sent && recovered(train, recover).await {
+            probe = self::probe(train, &payload, wait).await;
+        }
+        progress(&probe);
+        let (sent, disconnected) = (probe.sent, probe.disconnected);
+        report.probes.push(probe);
+
+        if !sent || (disconnected && !recovered(train, recover).await) {
+            return report;
+        }
+    }
+    report.completed = true;
+    report
+}
+
+/// One line describing a probe, for the text output.
+pub fn describe(probe: &Probe) -> String {
+    let mut line = probe.payload.clone();
+    if let Some(err) = &probe.error {
+        line += &format!("  error: {err}");
+    }
+    for notification in &probe.notifications {
+        line += &format!("  <- {notification:?}");
+    }
+    if probe.disconnected {
+        line += "  DISCONNECTED";
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use brio_smart_tech::{BrioSmartTech, MockTransport, ReconnectPolicy};
+
+    use super::*;
+
+    fn words(words: &str) -> Vec<String> {
+        words.split_whitespace().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_pattern() {
+        let pattern = Pattern::parse(&words("56 0xaa f0-f3")).unwrap();
+        assert_eq!(pattern.count(), 4);
+        assert_eq!(pattern.payloads().last(), Some(vec![0x56, 0xaa, 0xf3]));
+
+        let pattern = Pattern::parse(&words("00-01 fe-ff")).unwrap();
+        assert_eq!(
+            pattern.payloads().collect::<Vec<_>>(),
+            vec![
+                vec![0x00, 0xfe],
+                vec![0x00, 0xff],
+                vec![0x01, 0xfe],
+                vec![0x01, 0xff],
+            ]
+        );
+
+        let full_range = "00-ff ".repeat(8);
+        for pattern in
+            ["", "ff-00", "1g", "00-", "00-ff 00-ff 00-01", &full_range]
+        {
+            assert!(Pattern::parse(&words(pattern)).is_err(), "{pattern}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sweep() {
+        let mock = MockTransport::new();
+        let train = BrioSmartTech::with_reconnect(
+            mock.clone(),
+            ReconnectPolicy {
+                check_interval: Duration::from_millis(5),
+                retry_interval: Duration::from_millis(5),
+                max_attempts: Some(1),
+            },
+        )
+        .await
+        .unwrap();
+        mock.respond_to(
+            [0xaa, 0x01, 0x07, 0xf8],
+            [vec![0xaa, 0x02, 0x07, 0x33, 0xc4]],
+        );
+
+        let words = words("06-08");
+        let pattern = Pattern::parse(&words).unwrap();
+        let wait = Duration::from_millis(20);
+        let report = sweep(&train, &pattern, &words, wait, wait, |_| {}).await;
+        assert!(report.completed);
+        assert_eq!(report.probes.len(), 3);
+        assert!(report.probes[0].notifications.is_empty());
+        assert_eq!(
+            report.probes[1].notifications,
+            vec![Notification::Unknown(vec![0x07, 0x33])]
+        );
+        assert!(report.probes.iter().all(|probe| !probe.disconnected));
+
+        // A payload the link dropped before sending is sent once it is back.
+        mock.take_written_frames();
+        let drop_link = mock.clone();
+        let report = sweep(&train, &pattern, &words, wait, wait, |probe| {
+            if probe.payload == "06" {
+                drop_link.set_connected(false);
+            }
+        })
+        .await;
+        assert!(report.completed);
+        assert!(report.probes.iter().all(|probe| probe.sent));
+        let written = mock.written_frames();
+        for (byte, checksum) in [(0x06, 0xf9), (0x07, 0xf8), (0x08, 0xf7)] {
+            let frame = vec![0xaa, 0x01, byte, checksum];
+            assert!(written.contains(&frame), "{byte}");
+        }
+        assert!(report.summary().starts_with("3 sent"));
+
+        // The locomotive going away for good stops the sweep.
+        mock.set_reachable(false);
+        mock.set_connected(false);
+        let report = sweep(&train, &pattern, &words, wait, wait, |_| {}).await;
+        assert!(!report.completed);
+        assert_eq!(report.probes.len(), 1);
+        assert!(!report.probes[0].sent);
+        assert!(report.probes[0].disconnected);
+        assert!(report.summary().starts_with("0 sent"));
+        assert!(report.summary().ends_with("sweep aborted"));
+    }
+}
@@ -0,0 +1,311 @@
+//! Dashboard driving the locomotive from the keyboard.
+
+use std::{collections::VecDeque, error::Error};
+
+use brio_smart_tech::{
+    Color, ConnectionState, Direction, Motion, Speed, TrainHandle, TrainState,
+};
+use futures::StreamExt;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{Event, EventStream, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color as TermColor, Style, Stylize},
+    text::Line,
+    widgets::{Block, Gauge, List, Paragraph},
+};
+use strum::IntoEnumIterator;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Number of notifications kept in the log.
+const LOG_CAPACITY: usize = 100;
+
+const HELP: &str = "↑ faster forward  ↓ faster backward  space stop  0-9 w r \
+                    color  +/- intensity  q quit";
+
+/// What a key press asks for.
+#[derive(Debug, PartialEq)]
+enum Input {
+    /// Change the velocity by one step, positive towards forward.
+    Accelerate(i8),
+    Stop,
+    Color(Color),
+    Intensity(i8),
+    Quit,
+}
+
+fn input(key: KeyCode) -> Option<Input> {
+    Some(match key {
+        KeyCode::Up => Input::Accelerate(1),
+        KeyCode::Down => Input::Accelerate(-1),
+        KeyCode::Char(' ') => Input::Stop,
+        KeyCode::Char('+') => Input::Intensity(1),
+        KeyCode::Char('-') => Input::Intensity(-1),
+        KeyCode::Char('q') | KeyCode::Esc => Input::Quit,
+        // The colors past the digits.
+        KeyCode::Char('w') => Input::Color(Color::White),
+        KeyCode::Char('r') => Input::Color(Color::RedBackward),
+        KeyCode::Char(digit) => {
+            let index = digit.to_digit(10)?;
+            Input::Color(Color::iter().nth(index as usize)?)
+        }
+        _ => return None,
+    })
+}
+
+/// Motion one step away from `motion`, going through a stop when reversing.
+fn accelerate(motion: Motion, step: i8) -> Motion {
+    let speed = motion.speed.get() as i8;
+    let velocity = match motion.direction {
+        Direction::Forward => speed,
+        Direction::Backward => -speed,
+    };
+    let velocity = (velocity + step).clamp(-7, 7);
+    let direction = if velocity < 0 {
+        Direction::Backward
+    } else {
+        Direction::Forward
+    };
+    // Clamped to the valid speeds above.
+    Motion::new(direction, Speed::new(velocity.unsigned_abs()).unwrap())
+}
+
+/// Terminal color closest to the light of the locomotive.
+fn preview(color: Color) -> TermColor {
+    match color {
+        Color::Off => TermColor::Black,
+        Color::Yellow => TermColor::Yellow,
+        Color::Orange => TermColor::Rgb(255, 140, 0),
+        Color::Red => TermColor::Red,
+        Color::Pink => TermColor::Rgb(255, 105, 180),
+        Color::Purple => TermColor::Magenta,
+        Color::Blue => TermColor::Blue,
+        Color::LightBlue => TermColor::LightBlue,
+        Color::Cyan => TermColor::Cyan,
+        Color::Green => TermColor::Green,
+        Color::White => TermColor::White,
+        Color::RedBackward => TermColor::LightRed,
+    }
+}
+
+struct App {
+    train: TrainHandle,
+    connection_state: ConnectionState,
+    state: TrainState,
+    /// Most recent entries first.
+    log: VecDeque<String>,
+}
+
+impl App {
+    fn log(&mut self, entry: String) {
+        if self.log.len() == LOG_CAPACITY {
+            self.log.pop_back();
+        }
+        self.log.push_front(entry);
+    }
+
+    /// Act upon a key press, returning `false` to quit.
+    async fn handle(&mut self, input: Input) -> bool {
+        let (color, intensity) = self.state.color.unwrap_or((Color::Off, 0));
+        let result = match input {
+            Input::Accelerate(step) => {
+                let motion = accelerate(self.state.motion, step);
+                self.train.drive(motion.direction, motion.speed).await
+            }
+            Input::Stop => self.train.stop().await,
+            Input::Color(color) => {
+                // Turning the lights on from off shows them at full intensity.
+                let intensity = if intensity == 0 { 15 } else { intensity };
+                self.train.set_color(color, intensity).await
+            }
+            Input::Intensity(step) => {
+                let intensity = intensity.saturating_add_signed(step).min(15);
+                self.train.set_color(color, intensity).await
+            }
+            Input::Quit => return false,
+        };
+        if let Err(err) = result {
+            self.log(format!("Error: {err}"));
+        }
+        self.state = self.train.state();
+        true
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [status, speed, light, log, help] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let connected = if self.connection_state == ConnectionState::Ready {
+            TermColor::Green
+        } else {
+            TermColor::Red
+        };
+        frame.render_widget(
+            Paragraph::new(format!("{:?}", self.connection_state))
+                .fg(connected)
+                .block(Block::bordered().title("Connection")),
+            status,
+        );
+
+        let motion = self.state.motion;
+        let label = if motion.is_stopped() {
+            "stopped".to_string()
+        } else {
+            format!("{:?} {}", motion.direction, motion.speed).to_lowercase()
+        };
+        frame.render_widget(
+            Gauge::default()
+                .block(Block::bordered().title("Speed"))
+                .ratio(f64::from(motion.speed.get()) / 7.0)
+                .label(label),
+            speed,
+        );
+
+        let (title, style) = match self.state.color {
+            Some((color, intensity)) => (
+                format!("Light: {color} {intensity}"),
+                Style::new().bg(preview(color)),
+            ),
+            None => ("Light: unknown".to_string(), Style::new()),
+        };
+        frame.render_widget(Block::bordered().title(title).style(style), light);
+
+        frame.render_widget(
+            List::new(self.log.iter().map(String::as_str))
+                .block(Block::bordered().title("Notifications")),
+            log,
+        );
+        frame.render_widget(Line::from(HELP).dim(), help);
+    }
+}
+
+/// Show the dashboard until the user quits.
+pub async fn run(train: &TrainHandle) -> Result<(), Box<dyn Error>> {
+    let mut terminal = ratatui::init();
+    let result = run_app(&mut terminal, train).await;
+    ratatui::restore();
+    result
+}
+
+async fn run_app(
+    terminal: &mut DefaultTerminal,
+    train: &TrainHandle,
+) -> Result<(), Box<dyn Error>> {
+    let mut events = EventStream::new();
+    let mut notifications = train.subscribe();
+    let mut connection_state = train.connection_state();
+    let mut app = App {
+        train: train.clone(),
+        connection_state: *connection_state.borrow_and_update(),
+        state: train.state(),
+        log: VecDeque::new(),
+    };
+
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+
+        tokio::select! {
+            event = events.next() => match event {
+                Some(Ok(Event::Key(key))) => {
+                    if key.kind == KeyEventKind::Press
+                        && let Some(input) = input(key.code)
+                        && !app.handle(input).await
+                    {
+                        return Ok(());
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err.into()),
+                None => return Ok(()),
+            },
+            notification = notifications.recv() => {
+                match notification {
+                    Ok(notification) => app.log(format!("{notification:?}")),
+                    Err(RecvError::Lagged(count)) => {
+                        app.log(format!("Missed {count} notifications"));
+                    }
+                    Err(RecvError::Closed) => {
+                        return Err("connection closed".into());
+                    }
+                }
+                app.state = train.state();
+            }
+            Ok(()) = connection_state.changed() => {
+                app.connection_state = *connection_state.borrow_and_update();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use brio_smart_tech::{BrioSmartTech, MockTransport};
+    use ratatui::{Terminal, backend::TestBackend};
+
+    use super::*;
+
+    #[test]
+    fn test_input() {
+        assert_eq!(input(KeyCode::Char('0')), Some(Input::Color(Color::Off)));
+        assert_eq!(input(KeyCode::Char('9')), Some(Input::Color(Color::Green)));
+        // Every color has a key.
+        let colors: Vec<Option<Input>> = "0123456789wr"
+            .chars()
+            .map(|c| input(KeyCode::Char(c)))
+            .collect();
+        assert_eq!(
+            colors,
+            Color::iter()
+                .map(|c| Some(Input::Color(c)))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(input(KeyCode::Char('x')), None);
+
+        let forward =
+            |speed| Motion::new(Direction::Forward, Speed::new(speed).unwrap());
+        let backward = |speed| {
+            Motion::new(Direction::Backward, Speed::new(speed).unwrap())
+        };
+        assert_eq!(accelerate(forward(1), -1), forward(0));
+        assert_eq!(accelerate(forward(0), -1), backward(1));
+        assert_eq!(accelerate(backward(7), -1), backward(7));
+        assert_eq!(accelerate(forward(6), 1), forward(7));
+    }
+
+    #[tokio::test]
+    async fn test_draw() {
+        let train = BrioSmartTech::with_transport(MockTransport::new())
+            .await
+            .unwrap();
+        let mut app = App {
+            connection_state: ConnectionState::Ready,
+            state: train.state(),
+            train,
+            log: VecDeque::new(),
+        };
+        assert!(app.handle(Input::Accelerate(-1)).await);
+        assert!(app.handle(Input::Color(Color::Blue)).await);
+        app.log("Unknown([3, 7])".to_string());
+
+        let mut terminal = Terminal::new(TestBackend::new(60, 16)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        for text in ["Ready", "backward 1", "Light: blue 15", "Unknown([3, 7])"]
+        {
+            assert!(screen.contains(text), "{text}");
+        }
+        assert!(!app.handle(Input::Quit).await);
+    }
+}
@@ -14,31 +14,30 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let central = adapters.first().unwrap();
 
     println!("Searching for train");
-    let train = BrioSmartTech::new(central)
-        .await?;
+    let train = BrioSmartTech::new(central).await?;
 
     println!("Sending different colors");
     for c in Color::iter() {
         println!("Color {c:?}");
         for i in 0..16 {
-            train.lock().await.set_color(c, i).await?;
+            train.set_color(c, i).await?;
             sleep(Duration::from_millis(100)).await;
         }
     }
 
-    train.lock().await.set_color(Color::White, 15).await?;
+    train.set_color(Color::White, 15).await?;
     sleep(Duration::from_millis(300)).await;
 
     println!("Forward");
-    train.lock().await.forward(7).await?;
+    train.forward(7).await?;
     sleep(Duration::from_secs(10)).await;
 
     println!("Backward");
-    train.lock().await.backward(7).await?;
+    train.backward(7).await?;
     sleep(Duration::from_secs(1)).await;
 
     println!("Stop");
-    train.lock().await.stop().await?;
+    train.stop().await?;
     sleep(Duration::from_millis(300)).await;
 
     Ok(())
@@ -0,0 +1,146 @@
+use btleplug::{api::BDAddr, platform::Adapter};
+use tokio::{sync::watch, time::Duration};
+
+#[cfg(doc)]
+use crate::BrioError;
+use crate::{
+    BrioSmartTech, BtleplugTransport, ConnectionState, ReconnectPolicy, Result,
+    TrainHandle, discovery,
+};
+
+/// How long to scan for the locomotive before giving up, by default.
+pub const DEFAULT_SCAN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Which locomotive to connect to.
+#[derive(Debug, Clone, Default)]
+enum Selector {
+    /// The first one found.
+    #[default]
+    Any,
+    Address(BDAddr),
+    Name(String),
+}
+
+impl Selector {
+    fn matches(&self, address: BDAddr, name: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Address(wanted) => *wanted == address,
+            Self::Name(wanted) => wanted == name,
+        }
+    }
+}
+
+/// Configure how to find and connect to a locomotive.
+#[derive(Debug, Clone)]
+pub struct BrioSmartTechBuilder {
+    selector: Selector,
+    scan_timeout: Duration,
+    reconnect: Option<ReconnectPolicy>,
+    connection_state: watch::Sender<ConnectionState>,
+}
+
+impl BrioSmartTechBuilder {
+    pub fn new() -> Self {
+        Self {
+            selector: Selector::Any,
+            scan_timeout: DEFAULT_SCAN_TIMEOUT,
+            reconnect: None,
+            connection_state: watch::Sender::new(ConnectionState::Disconnected),
+        }
+    }
+
+    /// Only connect to the locomotive with this Bluetooth address.
+    pub fn address(mut self, address: BDAddr) -> Self {
+        self.selector = Selector::Address(address);
+        self
+    }
+
+    /// Only connect to the locomotive advertising exactly this name.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.selector = Selector::Name(name.into());
+        self
+    }
+
+    /// Give up if the locomotive is not found within `timeout`, when
+    /// connecting and on each reconnection attempt.
+    pub fn scan_timeout(mut self, timeout: Duration) -> Self {
+        self.scan_timeout = timeout;
+        self
+    }
+
+    /// Reconnect according to `policy` whenever the link drops, see
+    /// [`BrioSmartTech::with_reconnect`].
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// Follow the state of the link, starting with the scan.
+    ///
+    /// The same channel is returned by
+    /// [`TrainHandle::connection_state`](crate::TrainHandle::connection_state)
+    /// once connected.
+    pub fn connection_state(&self) -> watch::Receiver<ConnectionState> {
+        self.connection_state.subscribe()
+    }
+
+    /// Scan for the locomotive and connect to it.
+    ///
+    /// Fails with [`BrioError::DeviceNotFound`] if no matching locomotive
+    /// shows up before the scan timeout.
+    pub async fn connect(self, central: &Adapter) -> Result<TrainHandle> {
+        let connection_state = self.connection_state.clone();
+        let result = self.try_connect(central).await;
+        if result.is_err() {
+            connection_state.send_replace(ConnectionState::Disconnected);
+        }
+        result
+    }
+
+    async fn try_connect(self, central: &Adapter) -> Result<TrainHandle> {
+        self.connection_state
+            .send_replace(ConnectionState::Scanning);
+        let train = discovery::scan_for(central, self.scan_timeout, |train| {
+            self.selector.matches(train.address, &train.name)
+        })
+        .await?;
+
+        let transport = BtleplugTransport::connect_reporting(
+            central,
+            train.peripheral().clone(),
+            self.scan_timeout,
+            &self.connection_state,
+        )
+        .await?;
+        BrioSmartTech::start(transport, self.reconnect, self.connection_state)
+            .await
+    }
+}
+
+impl Default for BrioSmartTechBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selector() {
+        let address = BDAddr::from([0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc]);
+        let other = BDAddr::from([0; 6]);
+
+        assert!(Selector::Any.matches(address, "Smart 2.0"));
+        assert!(Selector::Address(address).matches(address, "Smart 2.0"));
+        assert!(!Selector::Address(address).matches(other, "Smart 2.0"));
+        assert!(
+            Selector::Name("Smart 2.0 A".into()).matches(other, "Smart 2.0 A")
+        );
+        assert!(
+            !Selector::Name("Smart 2.0 A".into()).matches(other, "Smart 2.0 B")
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Commands understood by the locomotive.
+
+use crate::{BrioError, Color, Motion, Result, SoundEffect, SoundTheme};
+
+const SET_SPEED: u8 = 0x01;
+const SET_COLOR: u8 = 0x02;
+const SET_SOUND: [u8; 2] = [0x56, 0xaa];
+
+/// A command, as carried in the payload of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Command {
+    /// Set the speed and direction.
+    SetSpeed(Motion),
+    /// Set the light color, `intensity` ranging from 0 to 15.
+    SetColor { color: Color, intensity: u8 },
+    /// Select the set of sounds played by the locomotive.
+    SetSoundTheme(SoundTheme),
+    /// Play one sound effect.
+    PlaySoundEffect(SoundEffect),
+}
+
+impl Command {
+    /// Serialize the command into a frame payload.
+    pub fn to_payload(&self) -> Result<Vec<u8>> {
+        Ok(match self {
+            Self::SetSpeed(motion) => vec![SET_SPEED, motion.to_speed_byte()],
+            Self::SetColor { color, intensity } => {
+                vec![SET_COLOR, color.get_command_value(*intensity)?]
+            }
+            Self::SetSoundTheme(sound_theme) => {
+                let mut payload = SET_SOUND.to_vec();
+                payload.push(sound_theme.get_command_value());
+                payload
+            }
+            Self::PlaySoundEffect(effect) => {
+                let mut payload = SET_SOUND.to_vec();
+                payload.push(effect.get_command_value());
+                payload
+            }
+        })
+    }
+
+    /// Parse a frame payload into a command.
+    pub fn from_payload(payload: &[u8]) -> Result<Self> {
+        let unknown = || {
+            BrioError::InvalidArgument(format!(
+                "unknown command {payload:02x?}"
+            ))
+        };
+
+        match *payload {
+            [SET_SPEED, speed] => Motion::from_speed_byte(speed)
+                .map(Self::SetSpeed)
+                .ok_or_else(unknown),
+            [SET_COLOR, value] => {
+                let (color, intensity) =
+                    Color::from_command_value(value).ok_or_else(unknown)?;
+                Ok(Self::SetColor { color, intensity })
+            }
+            [a, b, value] if [a, b] == SET_SOUND => {
+                SoundTheme::from_command_value(value)
+                    .map(Self::SetSoundTheme)
+                    .or_else(|| {
+                        SoundEffect::from_command_value(value)
+                            .map(Self::PlaySoundEffect)
+                    })
+                    .ok_or_else(unknown)
+            }
+            _ => Err(unknown()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use super::*;
+    use crate::{Direction, EffectSlot, Speed};
+
+    #[test]
+    fn test_payload_round_trip() {
+        let commands = [
+            Command::SetSpeed(Motion::default()),
+            Command::SetSpeed(Motion::new(Direction::Backward, Speed::MAX)),
+        ]
+        .into_iter()
+        .chain(Color::iter().flat_map(|color| {
+            (0..16).map(move |intensity| Command::SetColor { color, intensity })
+        }))
+        .chain(SoundTheme::iter().map(Command::SetSoundTheme))
+        .chain(SoundTheme::iter().flat_map(|theme| {
+            EffectSlot::iter().map(move |slot| {
+                Command::PlaySoundEffect(SoundEffect::new(theme, slot))
+            })
+        }));
+
+        for command in commands {
+            let payload = command.to_payload().unwrap();
+            assert_eq!(Command::from_payload(&payload).unwrap(), command);
+        }
+
+        assert_eq!(
+            Command::SetSoundTheme(SoundTheme::Horn)
+                .to_payload()
+                .unwrap(),
+            vec![0x56, 0xaa, 0xf2]
+        );
+    }
+
+    #[test]
+    fn test_invalid_payloads() {
+        assert!(
+            Command::SetColor {
+                color: Color::Red,
+                intensity: 16
+            }
+            .to_payload()
+            .is_err()
+        );
+        for payload in [
+            &[][..],
+            &[0x01],
+            &[0x01, 0x09],
+            &[0x02, 0xc0],
+            &[0x56, 0xaa, 0xf4],
+            &[0x56, 0xaa, 0x10],
+            &[0x56, 0x00, 0xf0],
+            &[0x42, 0x00],
+        ] {
+            assert!(Command::from_payload(payload).is_err());
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let command = Command::SetColor {
+            color: Color::LightBlue,
+            intensity: 9,
+        };
+        let json = serde_json::to_string(&command).unwrap();
+        assert_eq!(
+            json,
+            r#"{"set-color":{"color":"light-blue","intensity":9}}"#
+        );
+        assert_eq!(serde_json::from_str::<Command>(&json).unwrap(), command);
+
+        let command =
+            Command::SetSpeed(Motion::new(Direction::Backward, Speed::MAX));
+        let json = serde_json::to_string(&command).unwrap();
+        assert_eq!(json, r#"{"set-speed":{"direction":"backward","speed":7}}"#);
+        assert_eq!(serde_json::from_str::<Command>(&json).unwrap(), command);
+        assert!(
+            serde_json::from_str::<Command>(
+                r#"{"set-speed":{"direction":"forward","speed":8}}"#
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,16 @@
+/// Lifecycle of the link with a locomotive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Looking for the locomotive.
+    Scanning,
+    /// Establishing the link.
+    Connecting,
+    /// Looking up the characteristics used by the protocol.
+    DiscoveringServices,
+    /// Commands can be sent.
+    Ready,
+    /// The link is down.
+    Disconnected,
+    /// The link dropped and is being restored.
+    Reconnecting,
+}
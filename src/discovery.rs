@@ -0,0 +1,138 @@
+//! Scanning for nearby locomotives.
+
+use std::collections::HashMap;
+
+use btleplug::{
+    api::{BDAddr, Central, Peripheral as _, PeripheralProperties, ScanFilter},
+    platform::{Adapter, Peripheral},
+};
+use tokio::time::{Duration, Instant, sleep};
+
+use crate::{BrioError, Result, brio_uuid};
+
+/// Interval at which the peripherals found while scanning are checked.
+const SCAN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A locomotive seen while scanning.
+#[derive(Debug, Clone)]
+pub struct DiscoveredTrain {
+    pub address: BDAddr,
+    pub name: String,
+    /// Signal strength of the last advertisement, in dBm.
+    pub rssi: Option<i16>,
+    /// Advertised manufacturer specific data, keyed by manufacturer ID.
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    peripheral: Peripheral,
+}
+
+impl DiscoveredTrain {
+    fn from_properties(
+        peripheral: Peripheral,
+        properties: PeripheralProperties,
+    ) -> Option<Self> {
+        let name = properties.local_name.filter(|name| is_train_name(name))?;
+
+        Some(Self {
+            address: properties.address,
+            name,
+            rssi: properties.rssi,
+            manufacturer_data: properties.manufacturer_data,
+            peripheral,
+        })
+    }
+
+    /// Peripheral to connect to, see
+    /// [`BtleplugTransport::connect`](crate::BtleplugTransport::connect).
+    pub fn peripheral(&self) -> &Peripheral {
+        &self.peripheral
+    }
+}
+
+fn is_train_name(name: &str) -> bool {
+    name.contains("Smart 2.0")
+}
+
+pub(crate) async fn start_scan(central: &Adapter) -> Result<()> {
+    // service and characteristic have the same uuid for the brio smart 2.0
+    let service_uuid = brio_uuid(1);
+
+    log::debug!("Scanning for devices with service UUID: {service_uuid}");
+    central
+        .start_scan(ScanFilter {
+            services: vec![service_uuid],
+        })
+        .await?;
+    Ok(())
+}
+
+/// Locomotives known to the adapter.
+pub(crate) async fn trains(central: &Adapter) -> Result<Vec<DiscoveredTrain>> {
+    let mut trains = Vec::new();
+
+    for p in central.peripherals().await? {
+        if let Some(properties) = p.properties().await?
+            && let Some(train) = DiscoveredTrain::from_properties(p, properties)
+        {
+            trains.push(train);
+        }
+    }
+    Ok(trains)
+}
+
+/// Scan until a locomotive accepted by `predicate` shows up.
+///
+/// Fails with [`BrioError::DeviceNotFound`] once `timeout` has elapsed.
+pub(crate) async fn scan_for(
+    central: &Adapter,
+    timeout: Duration,
+    predicate: impl Fn(&DiscoveredTrain) -> bool,
+) -> Result<DiscoveredTrain> {
+    let deadline = Instant::now() + timeout;
+
+    start_scan(central).await?;
+    let train = loop {
+        match trains(central).await {
+            Ok(trains) => {
+                if let Some(train) = trains.into_iter().find(&predicate) {
+                    break Ok(train);
+                }
+            }
+            Err(err) => break Err(err),
+        }
+        if Instant::now() >= deadline {
+            break Err(BrioError::DeviceNotFound);
+        }
+        sleep(SCAN_POLL_INTERVAL.min(deadline - Instant::now())).await;
+    };
+    central.stop_scan().await?;
+
+    train
+}
+
+/// Scan for `timeout` and list the locomotives found, closest first.
+pub async fn discover(
+    central: &Adapter,
+    timeout: Duration,
+) -> Result<Vec<DiscoveredTrain>> {
+    start_scan(central).await?;
+    sleep(timeout).await;
+
+    let trains = trains(central).await;
+    central.stop_scan().await?;
+
+    let mut trains = trains?;
+    trains.sort_by_key(|train| std::cmp::Reverse(train.rssi));
+    Ok(trains)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_train_name() {
+        assert!(is_train_name("Smart 2.0"));
+        assert!(is_train_name("BRIO Smart 2.0 1234"));
+        assert!(!is_train_name("Smart TV"));
+    }
+}
@@ -0,0 +1,344 @@
+//! Light animations built on [`TrainHandle::set_color`].
+
+use std::f64::consts::TAU;
+
+use tokio::{
+    task::JoinHandle,
+    time::{Duration, Instant, MissedTickBehavior, interval},
+};
+
+use crate::{BrioError, Color, Result, TrainHandle};
+
+/// Frames per second used when none is given.
+pub const DEFAULT_FRAME_RATE: u32 = 20;
+
+/// Highest frame rate accepted, far beyond what the link can carry.
+pub const MAX_FRAME_RATE: u32 = 1000;
+
+/// Highest intensity accepted by the lights.
+const MAX_INTENSITY: u8 = 15;
+
+/// Light animation, `intensity` ranging from 0 to 15.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    /// Alternate between `color` and off, each for half the period.
+    Blink {
+        color: Color,
+        intensity: u8,
+        period: Duration,
+    },
+    /// Smoothly bring `color` up to `intensity` and back down every period.
+    Pulse {
+        color: Color,
+        intensity: u8,
+        period: Duration,
+    },
+    /// Dim the `from` color down, then bring the `to` color up.
+    ///
+    /// Unlike the other effects, it ends once `duration` has elapsed.
+    Fade {
+        from: (Color, u8),
+        to: (Color, u8),
+        duration: Duration,
+    },
+    /// Go through every color, staying `step` on each.
+    Rainbow { intensity: u8, step: Duration },
+    /// Flash `color` for a single frame every period.
+    Strobe {
+        color: Color,
+        intensity: u8,
+        period: Duration,
+    },
+}
+
+impl Effect {
+    fn check(&self) -> Result<()> {
+        let (intensities, duration) = match *self {
+            Self::Blink {
+                intensity, period, ..
+            }
+            | Self::Pulse {
+                intensity, period, ..
+            }
+            | Self::Strobe {
+                intensity, period, ..
+            } => ([intensity, intensity], period),
+            Self::Fade { from, to, duration } => ([from.1, to.1], duration),
+            Self::Rainbow { intensity, step } => ([intensity, intensity], step),
+        };
+        if let Some(intensity) =
+            intensities.into_iter().find(|&i| i > MAX_INTENSITY)
+        {
+            return Err(BrioError::InvalidArgument(format!(
+                "intensity {intensity} is out of range 0..=15"
+            )));
+        }
+        if duration.is_zero() {
+            return Err(BrioError::InvalidArgument(
+                "effect period cannot be zero".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// How long the effect lasts, `None` when it runs until cancelled.
+    fn duration(&self) -> Option<Duration> {
+        match *self {
+            Self::Fade { duration, .. } => Some(duration),
+            _ => None,
+        }
+    }
+
+    /// Color and intensity to show `elapsed` after the start of the effect.
+    fn frame(
+        &self,
+        elapsed: Duration,
+        frame_interval: Duration,
+    ) -> (Color, u8) {
+        match *self {
+            Self::Blink {
+                color,
+                intensity,
+                period,
+            } => {
+                if phase(elapsed, period) < 0.5 {
+                    (color, intensity)
+                } else {
+                    (Color::Off, 0)
+                }
+            }
+            Self::Pulse {
+                color,
+                intensity,
+                period,
+            } => {
+                let level = (1.0 - (TAU * phase(elapsed, period)).cos()) / 2.0;
+                (color, scale(intensity, level))
+            }
+            Self::Fade { from, to, duration } => {
+                let progress =
+                    (elapsed.as_secs_f64() / duration.as_secs_f64()).min(1.0);
+                if progress < 0.5 {
+                    (from.0, scale(from.1, 1.0 - 2.0 * progress))
+                } else {
+                    (to.0, scale(to.1, 2.0 * progress - 1.0))
+                }
+            }
+            Self::Rainbow { intensity, step } => {
+                let steps = elapsed.as_nanos() / step.as_nanos();
+                let mut color = Color::Yellow;
+                // Off is skipped, leaving 11 colors to go through.
+                for _ in 0..steps % 11 {
+                    color = color.next();
+                }
+                (color, intensity)
+            }
+            Self::Strobe {
+                color,
+                intensity,
+                period,
+            } => {
+                let flash = frame_interval.as_secs_f64() / period.as_secs_f64();
+                if phase(elapsed, period) < flash {
+                    (color, intensity)
+                } else {
+                    (Color::Off, 0)
+                }
+            }
+        }
+    }
+}
+
+/// Position within the current period, between 0 and 1.
+fn phase(elapsed: Duration, period: Duration) -> f64 {
+    (elapsed.as_secs_f64() / period.as_secs_f64()).fract()
+}
+
+fn scale(intensity: u8, level: f64) -> u8 {
+    (f64::from(intensity) * level).round() as u8
+}
+
+/// Effect running in the background.
+///
+/// The effect is cancelled when the task is dropped, the lights being left
+/// as they were on the last frame.
+#[must_use = "the effect is cancelled when dropped"]
+pub struct EffectTask {
+    task: JoinHandle<Result<()>>,
+}
+
+impl EffectTask {
+    pub(crate) fn spawn(
+        train: TrainHandle,
+        effect: Effect,
+        frame_rate: u32,
+    ) -> Result<Self> {
+        effect.check()?;
+        if !(1..=MAX_FRAME_RATE).contains(&frame_rate) {
+            return Err(BrioError::InvalidArgument(format!(
+                "frame rate {frame_rate} is out of range 1..={MAX_FRAME_RATE}"
+            )));
+        }
+        let frame_interval = Duration::from_secs(1) / frame_rate;
+        // A flash lasts a frame, it needs another one to go off again.
+        if let Effect::Strobe { period, .. } = effect
+            && period < 2 * frame_interval
+        {
+            return Err(BrioError::InvalidArgument(format!(
+                "strobe period {period:?} is shorter than two frames at \
+                 {frame_rate} frames per second"
+            )));
+        }
+        let task = tokio::spawn(async move {
+            let mut frames = interval(frame_interval);
+            frames.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            let start = Instant::now();
+            let mut shown = None;
+
+            loop {
+                frames.tick().await;
+                let elapsed = start.elapsed();
+                let frame = effect.frame(elapsed, frame_interval);
+                // Only talk to the locomotive when something changes.
+                if shown != Some(frame) {
+                    train.set_color(frame.0, frame.1).await?;
+                    shown = Some(frame);
+                }
+                if effect.duration().is_some_and(|end| elapsed >= end) {
+                    return Ok(());
+                }
+            }
+        });
+        Ok(Self { task })
+    }
+
+    /// Stop the effect.
+    pub fn cancel(self) {
+        // Aborted on drop.
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Wait for the effect to end, reporting why it stopped early if it did.
+    ///
+    /// Only [`Effect::Fade`] ends on its own.
+    pub async fn wait(mut self) -> Result<()> {
+        (&mut self.task)
+            .await
+            .unwrap_or(Err(BrioError::Disconnected))
+    }
+}
+
+impl Drop for EffectTask {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BrioSmartTech, sim::SimTransport};
+
+    const FRAME: Duration = Duration::from_millis(50);
+
+    #[test]
+    fn test_frames() {
+        let second = Duration::from_secs(1);
+        let blink = Effect::Blink {
+            color: Color::Red,
+            intensity: 15,
+            period: second,
+        };
+        assert_eq!(blink.frame(second / 4, FRAME), (Color::Red, 15));
+        assert_eq!(blink.frame(second * 7 / 4, FRAME), (Color::Off, 0));
+
+        let pulse = Effect::Pulse {
+            color: Color::Blue,
+            intensity: 10,
+            period: second,
+        };
+        assert_eq!(pulse.frame(Duration::ZERO, FRAME), (Color::Blue, 0));
+        assert_eq!(pulse.frame(second / 2, FRAME), (Color::Blue, 10));
+
+        let fade = Effect::Fade {
+            from: (Color::Green, 8),
+            to: (Color::White, 12),
+            duration: second,
+        };
+        assert_eq!(fade.frame(second / 4, FRAME), (Color::Green, 4));
+        assert_eq!(fade.frame(second * 3 / 4, FRAME), (Color::White, 6));
+        assert_eq!(fade.frame(second * 2, FRAME), (Color::White, 12));
+
+        let rainbow = Effect::Rainbow {
+            intensity: 5,
+            step: second,
+        };
+        assert_eq!(rainbow.frame(Duration::ZERO, FRAME), (Color::Yellow, 5));
+        assert_eq!(rainbow.frame(second * 10, FRAME), (Color::RedBackward, 5));
+        assert_eq!(rainbow.frame(second * 11, FRAME), (Color::Yellow, 5));
+
+        let strobe = Effect::Strobe {
+            color: Color::White,
+            intensity: 15,
+            period: second,
+        };
+        assert_eq!(strobe.frame(second / 100, FRAME), (Color::White, 15));
+        assert_eq!(strobe.frame(second / 10, FRAME), (Color::Off, 0));
+
+        assert!(matches!(
+            Effect::Rainbow {
+                intensity: 16,
+                step: second
+            }
+            .check(),
+            Err(BrioError::InvalidArgument(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_effect_task() {
+        let sim = SimTransport::new();
+        let train = BrioSmartTech::with_transport(sim.clone()).await.unwrap();
+
+        let fade = Effect::Fade {
+            from: (Color::Red, 4),
+            to: (Color::Blue, 4),
+            duration: Duration::from_millis(100),
+        };
+        train.start_effect(fade, 100).unwrap().wait().await.unwrap();
+        assert_eq!(sim.locomotive().color(), (Color::Blue, 4));
+
+        let blink = Effect::Blink {
+            color: Color::Green,
+            intensity: 15,
+            period: Duration::from_millis(20),
+        };
+        for frame_rate in [0, MAX_FRAME_RATE + 1, u32::MAX] {
+            assert!(matches!(
+                train.start_effect(blink, frame_rate),
+                Err(BrioError::InvalidArgument(_))
+            ));
+        }
+        let strobe = Effect::Strobe {
+            color: Color::White,
+            intensity: 15,
+            period: Duration::from_millis(15),
+        };
+        assert!(matches!(
+            train.start_effect(strobe, 100),
+            Err(BrioError::InvalidArgument(_))
+        ));
+        let task = train.start_effect(blink, 100).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        task.cancel();
+        // Let a frame already on its way go through.
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let frames = sim.link().written_frames().len();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(sim.link().written_frames().len(), frames);
+    }
+}
@@ -0,0 +1,38 @@
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::FrameError;
+
+/// Errors reported while talking to a Brio Smart Tech locomotive.
+#[derive(Debug, Error)]
+pub enum BrioError {
+    /// A value passed to the API is outside of what the protocol accepts.
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+
+    /// No matching locomotive could be found while scanning.
+    #[error("no Brio Smart Tech device found")]
+    DeviceNotFound,
+
+    /// The device does not expose a characteristic the protocol relies on.
+    #[error("characteristic {0} is missing")]
+    CharacteristicMissing(Uuid),
+
+    /// Error reported by the Bluetooth Low Energy stack.
+    #[error("Bluetooth error: {0}")]
+    Ble(#[from] btleplug::Error),
+
+    /// Data received from the device does not follow the framing.
+    #[error("corrupted frame: {0}")]
+    FrameCorruption(#[from] FrameError),
+
+    /// The operation did not complete in time.
+    #[error("operation timed out")]
+    Timeout,
+
+    /// The link with the device is down.
+    #[error("device disconnected")]
+    Disconnected,
+}
+
+pub type Result<T> = std::result::Result<T, BrioError>;
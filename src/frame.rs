@@ -0,0 +1,331 @@
+//! Framing of the data exchanged with the locomotive.
+//!
+//! Every command and notification is carried in a frame made of the `0xaa`
+//! sync byte, the payload length, the payload and a checksum.
+
+use thiserror::Error;
+
+/// Byte starting every frame.
+pub const SYNC: u8 = 0xaa;
+
+/// Ways a frame can be malformed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FrameError {
+    #[error("expected sync byte 0xaa, found {0:#04x}")]
+    Sync(u8),
+
+    #[error("frame is truncated")]
+    Truncated,
+
+    #[error("frame of {actual} bytes, its length byte announces {expected}")]
+    Length { expected: usize, actual: usize },
+
+    #[error("bad checksum {found:#04x}, expected {expected:#04x}")]
+    Checksum { expected: u8, found: u8 },
+
+    #[error("payload of {0} bytes does not fit in a frame")]
+    TooLong(usize),
+}
+
+/// A frame, holding its payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    payload: Vec<u8>,
+}
+
+fn compute_checksum(payload: &[u8]) -> u8 {
+    let sum: u16 = (payload.len() as u16)
+        + payload.iter().map(|x| u16::from(*x)).sum::<u16>();
+    ((0x100 - (sum & 0xFF)) & 0xFF) as u8
+}
+
+impl Frame {
+    pub fn new(payload: impl Into<Vec<u8>>) -> Result<Self, FrameError> {
+        let payload = payload.into();
+        if payload.len() > usize::from(u8::MAX) {
+            return Err(FrameError::TooLong(payload.len()));
+        }
+        Ok(Self { payload })
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    pub fn into_payload(self) -> Vec<u8> {
+        self.payload
+    }
+
+    /// Serialize the frame, ready to be written to the device.
+    pub fn encode(&self) -> Vec<u8> {
+        // Insert first a byte indicating the number of bytes the payload has.
+        // This byte enters in the computation of the checksum.
+        let mut data = Vec::with_capacity(self.payload.len() + 3);
+        data.push(SYNC);
+        data.push(self.payload.len() as u8);
+        data.extend(&self.payload);
+        data.push(compute_checksum(&self.payload));
+
+        data
+    }
+
+    /// Parse data holding exactly one frame.
+    pub fn decode(data: &[u8]) -> Result<Self, FrameError> {
+        match *data {
+            [] | [SYNC] => return Err(FrameError::Truncated),
+            [SYNC, ..] => {}
+            [byte, ..] => return Err(FrameError::Sync(byte)),
+        }
+
+        let expected = usize::from(data[1]) + 3;
+        if data.len() < expected {
+            return Err(FrameError::Truncated);
+        }
+        if data.len() > expected {
+            return Err(FrameError::Length {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        let payload = &data[2..expected - 1];
+        let found = data[expected - 1];
+        let checksum = compute_checksum(payload);
+        if found != checksum {
+            return Err(FrameError::Checksum {
+                expected: checksum,
+                found,
+            });
+        }
+
+        Ok(Self {
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+/// Decoder extracting frames from a byte stream.
+///
+/// Data can be pushed in arbitrary chunks: a chunk may hold several frames or
+/// only part of one. Chunks known to end on a frame boundary, such as the
+/// notifications of the locomotive, are pushed with
+/// [`FrameDecoder::push_complete`] instead, so that a corrupted length byte
+/// does not hold the following frames back. Malformed data is reported once,
+/// then the decoder resynchronises on the next sync byte.
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    /// No more data is expected for the frames buffered.
+    complete: bool,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append received data.
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+        self.complete = false;
+    }
+
+    /// Append received data ending on a frame boundary.
+    ///
+    /// A frame left incomplete by `data` is reported as malformed rather than
+    /// waited for.
+    pub fn push_complete(&mut self, data: &[u8]) {
+        self.push(data);
+        self.complete = true;
+    }
+
+    /// Number of buffered bytes not yet decoded.
+    pub fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Next frame or error available, `None` if more data is needed.
+    pub fn next_frame(&mut self) -> Option<Result<Frame, FrameError>> {
+        let &first = self.buffer.first()?;
+        if first != SYNC {
+            self.skip_to_sync(0);
+            return Some(Err(FrameError::Sync(first)));
+        }
+
+        let Some(&len) = self.buffer.get(1) else {
+            if !self.complete {
+                return None;
+            }
+            self.buffer.clear();
+            return Some(Err(FrameError::Truncated));
+        };
+        let len = usize::from(len) + 3;
+        if self.buffer.len() < len {
+            if !self.complete {
+                return None;
+            }
+            // The length byte is corrupted, the frames after it are not.
+            let actual = self.buffer.len();
+            self.skip_to_sync(1);
+            return Some(Err(FrameError::Length {
+                expected: len,
+                actual,
+            }));
+        }
+
+        match Frame::decode(&self.buffer[..len]) {
+            Ok(frame) => {
+                self.buffer.drain(..len);
+                Some(Ok(frame))
+            }
+            Err(err) => {
+                // The length byte itself may be corrupted, look for the next
+                // frame right after this sync byte.
+                self.skip_to_sync(1);
+                Some(Err(err))
+            }
+        }
+    }
+
+    fn skip_to_sync(&mut self, from: usize) {
+        let start = self.buffer[from..]
+            .iter()
+            .position(|&byte| byte == SYNC)
+            .map_or(self.buffer.len(), |position| from + position);
+        self.buffer.drain(..start);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    fn command_data(payload: Vec<u8>) -> Vec<u8> {
+        Frame::new(payload).unwrap().encode()
+    }
+
+    #[test]
+    fn test_chksum() {
+        assert_eq!(
+            command_data(vec![
+                0x02,
+                Color::Blue.get_command_value(15).unwrap()
+            ]),
+            vec![0xaa, 0x02, 0x02, 0x6f, 0x8d]
+        );
+        assert_eq!(
+            command_data(vec![
+                0x02,
+                Color::LightBlue.get_command_value(15).unwrap()
+            ]),
+            vec![0xaa, 0x02, 0x02, 0x7f, 0x7d]
+        );
+
+        assert_eq!(
+            command_data(vec![0x01, 0x00]),
+            vec![0xaa, 0x02, 0x01, 0x00, 0xfd]
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            Frame::decode(&[0xaa, 0x02, 0x01, 0x00, 0xfd]),
+            Ok(Frame::new([0x01, 0x00]).unwrap())
+        );
+        assert_eq!(
+            Frame::decode(&[0xaa, 0x02, 0x01, 0x00, 0xfe]),
+            Err(FrameError::Checksum {
+                expected: 0xfd,
+                found: 0xfe
+            })
+        );
+        assert_eq!(
+            Frame::decode(&[0xab, 0x02, 0x01, 0x00, 0xfd]),
+            Err(FrameError::Sync(0xab))
+        );
+        assert_eq!(
+            Frame::decode(&[0xaa, 0x02, 0x01, 0x00]),
+            Err(FrameError::Truncated)
+        );
+        assert_eq!(
+            Frame::decode(&[0xaa, 0x02, 0x01, 0x00, 0xfd, 0x00]),
+            Err(FrameError::Length {
+                expected: 5,
+                actual: 6
+            })
+        );
+        assert_eq!(Frame::new(vec![0; 256]), Err(FrameError::TooLong(256)));
+    }
+
+    #[test]
+    fn test_decoder() {
+        let mut decoder = FrameDecoder::new();
+
+        // Fragmented frame.
+        decoder.push(&[0xaa, 0x02, 0x01]);
+        assert_eq!(decoder.next_frame(), None);
+        decoder.push(&[0x00, 0xfd]);
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Ok(Frame::new([0x01, 0x00]).unwrap()))
+        );
+        assert_eq!(decoder.next_frame(), None);
+
+        // Garbage, a corrupted frame, then two concatenated frames.
+        decoder.push(&[0x12, 0x34, 0xaa, 0x02, 0x01, 0x00, 0x00]);
+        decoder.push(&[0xaa, 0x02, 0x01, 0x00, 0xfd]);
+        decoder.push(&[0xaa, 0x02, 0x02, 0x6f, 0x8d]);
+        assert_eq!(decoder.next_frame(), Some(Err(FrameError::Sync(0x12))));
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Err(FrameError::Checksum {
+                expected: 0xfd,
+                found: 0x00
+            }))
+        );
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Ok(Frame::new([0x01, 0x00]).unwrap()))
+        );
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Ok(Frame::new([0x02, 0x6f]).unwrap()))
+        );
+        assert_eq!(decoder.next_frame(), None);
+        assert_eq!(decoder.pending(), 0);
+    }
+
+    #[test]
+    fn test_corrupted_length() {
+        let stop = [0xaa, 0x02, 0x01, 0x00, 0xfd];
+        let mut data = vec![0xaa, 0xff, 0x01, 0x00, 0xfd];
+        for _ in 0..20 {
+            data.extend(stop);
+        }
+
+        let mut decoder = FrameDecoder::new();
+        decoder.push_complete(&data);
+        assert_eq!(
+            decoder.next_frame(),
+            Some(Err(FrameError::Length {
+                expected: 258,
+                actual: 105
+            }))
+        );
+        for _ in 0..20 {
+            assert_eq!(
+                decoder.next_frame(),
+                Some(Ok(Frame::new([0x01, 0x00]).unwrap()))
+            );
+        }
+        assert_eq!(decoder.next_frame(), None);
+        assert_eq!(decoder.pending(), 0);
+
+        // A lone sync byte ending the data is dropped as well.
+        decoder.push_complete(&[0xaa]);
+        assert_eq!(decoder.next_frame(), Some(Err(FrameError::Truncated)));
+        assert_eq!(decoder.pending(), 0);
+    }
+}
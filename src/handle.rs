@@ -0,0 +1,446 @@
+use tokio::{
+    sync::{broadcast, mpsc, oneshot, watch},
+    time::{Instant, sleep_until},
+};
+
+use crate::{
+    BrioError, Color, Command, ConnectionState, Direction, Effect, EffectTask,
+    Headlights, Motion, Notification, Result, SoundEffect, SoundTheme, Speed,
+    TrainState, check_speed,
+    ramp::{self, Ramp},
+};
+
+/// Requests served by the task owning the connection.
+pub(crate) enum Request {
+    /// Speed changes made by a ramp carry the generation of the ramp, and
+    /// are dropped once it is superseded.
+    Send(Command, Option<u64>, oneshot::Sender<Result<()>>),
+    #[cfg(feature = "unstable-raw")]
+    SendRaw(Vec<u8>, oneshot::Sender<Result<()>>),
+    IsConnected(oneshot::Sender<Result<bool>>),
+    SetHeadlights(Option<Headlights>, oneshot::Sender<Result<()>>),
+    Disconnect {
+        lights_off: bool,
+        reply: oneshot::Sender<Result<()>>,
+    },
+}
+
+impl Request {
+    /// Answer the request while the link is down.
+    pub(crate) fn reject(self) {
+        // The requester may have given up waiting, nothing to do then.
+        match self {
+            #[cfg(feature = "unstable-raw")]
+            Self::SendRaw(_, reply) => {
+                let _ = reply.send(Err(BrioError::Disconnected));
+            }
+            Self::Send(_, _, reply)
+            | Self::SetHeadlights(_, reply)
+            | Self::Disconnect { reply, .. } => {
+                let _ = reply.send(Err(BrioError::Disconnected));
+            }
+            Self::IsConnected(reply) => {
+                let _ = reply.send(Ok(false));
+            }
+        }
+    }
+}
+
+/// Handle controlling a locomotive.
+///
+/// Requests are forwarded to the background task owning the connection, so
+/// the handle is cheap to clone and several tasks can control the same
+/// locomotive concurrently. The connection is kept until every handle is
+/// dropped or [`TrainHandle::disconnect`] is called. Dropping the last handle
+/// stops the locomotive, on a best-effort basis.
+#[derive(Clone)]
+pub struct TrainHandle {
+    requests: mpsc::Sender<Request>,
+    notifications: broadcast::WeakSender<Notification>,
+    connection_state: watch::Receiver<ConnectionState>,
+    state: watch::Receiver<TrainState>,
+    /// Bumped by every speed change, superseding the ramp in progress.
+    speed_generation: watch::Sender<u64>,
+}
+
+impl TrainHandle {
+    pub(crate) fn new(
+        requests: mpsc::Sender<Request>,
+        notifications: &broadcast::Sender<Notification>,
+        connection_state: watch::Receiver<ConnectionState>,
+        state: watch::Receiver<TrainState>,
+        speed_generation: watch::Sender<u64>,
+    ) -> Self {
+        Self {
+            requests,
+            notifications: notifications.downgrade(),
+            connection_state,
+            state,
+            speed_generation,
+        }
+    }
+
+    pub(crate) async fn request<R>(
+        &self,
+        request: impl FnOnce(oneshot::Sender<Result<R>>) -> Request,
+    ) -> Result<R> {
+        let (reply, response) = oneshot::channel();
+        self.requests
+            .send(request(reply))
+            .await
+            .map_err(|_| BrioError::Disconnected)?;
+        response.await.map_err(|_| BrioError::Disconnected)?
+    }
+
+    /// Receive the notifications decoded from now on.
+    ///
+    /// Every subscriber gets its own copy of each notification.
+    pub fn subscribe(&self) -> broadcast::Receiver<Notification> {
+        match self.notifications.upgrade() {
+            Some(notifications) => notifications.subscribe(),
+            // The connection is gone, hand out a closed receiver.
+            None => broadcast::channel(1).1,
+        }
+    }
+
+    /// Follow the state of the link with the device.
+    pub fn connection_state(&self) -> watch::Receiver<ConnectionState> {
+        self.connection_state.clone()
+    }
+
+    /// Last known state of the locomotive, without talking to it.
+    pub fn state(&self) -> TrainState {
+        self.state.borrow().clone()
+    }
+
+    /// Follow the last known state of the locomotive.
+    pub fn watch_state(&self) -> watch::Receiver<TrainState> {
+        self.state.clone()
+    }
+
+    pub async fn is_connected(&self) -> Result<bool> {
+        self.request(Request::IsConnected).await
+    }
+
+    /// Stop the locomotive, optionally turn its lights off, and close the
+    /// connection.
+    ///
+    /// Every handle on the connection fails with [`BrioError::Disconnected`]
+    /// afterwards, and notification subscribers are closed.
+    pub async fn disconnect(&self, lights_off: bool) -> Result<()> {
+        self.request(|reply| Request::Disconnect { lights_off, reply })
+            .await
+    }
+
+    /// Send a command to the device.
+    ///
+    /// Setting the speed stops any ramp in progress.
+    pub async fn send(&self, command: Command) -> Result<()> {
+        if matches!(command, Command::SetSpeed(_)) {
+            self.supersede_ramp();
+        }
+        self.request(|reply| Request::Send(command, None, reply))
+            .await
+    }
+
+    /// Send a raw speed byte, as documented by [`Motion::from_speed_byte`].
+    ///
+    /// Only bytes sent as is are accepted: `0x01` and `0x11`, which would go
+    /// out as `0x00`, are rejected.
+    pub async fn set_speed(&self, speed: u8) -> Result<()> {
+        let motion = Motion::from_speed_byte(speed)
+            .filter(|motion| motion.to_speed_byte() == speed)
+            .ok_or_else(|| {
+                BrioError::InvalidArgument(format!(
+                    "speed byte {speed:#04x} cannot be sent as is"
+                ))
+            })?;
+        self.drive(motion.direction, motion.speed).await
+    }
+
+    /// Drive at `speed` in `direction`, stopping any ramp in progress.
+    pub async fn drive(
+        &self,
+        direction: Direction,
+        speed: Speed,
+    ) -> Result<()> {
+        self.send(Command::SetSpeed(Motion::new(direction, speed)))
+            .await
+    }
+
+    /// Progressively change to `speed` in `direction`, going through a stop
+    /// when reversing.
+    ///
+    /// Returns once the speed is reached, or early and without error when
+    /// another ramp or speed change is requested from any handle.
+    pub async fn ramp_to(
+        &self,
+        direction: Direction,
+        speed: Speed,
+        ramp: Ramp,
+    ) -> Result<()> {
+        let generation = self.supersede_ramp();
+        let mut speed_generation = self.speed_generation.subscribe();
+        let start = Instant::now();
+        let from = self.state.borrow().motion;
+
+        for (at, motion) in
+            ramp::plan(from, Motion::new(direction, speed), ramp)
+        {
+            tokio::select! {
+                () = sleep_until(start + at) => {}
+                _ = speed_generation.wait_for(|g| *g != generation) => {
+                    return Ok(());
+                }
+            }
+            let step = Command::SetSpeed(motion);
+            self.request(|reply| Request::Send(step, Some(generation), reply))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Mark the ramp in progress as superseded, returning the new generation.
+    fn supersede_ramp(&self) -> u64 {
+        let mut current = 0;
+        self.speed_generation.send_modify(|generation| {
+            *generation += 1;
+            current = *generation;
+        });
+        current
+    }
+
+    pub async fn forward(&self, speed: u8) -> Result<()> {
+        self.drive(Direction::Forward, check_speed(speed)?).await
+    }
+
+    pub async fn backward(&self, speed: u8) -> Result<()> {
+        self.drive(Direction::Backward, check_speed(speed)?).await
+    }
+
+    pub async fn stop(&self) -> Result<()> {
+        self.drive(Direction::Forward, Speed::STOP).await
+    }
+
+    pub async fn set_color(&self, color: Color, intensity: u8) -> Result<()> {
+        self.send(Command::SetColor { color, intensity }).await
+    }
+
+    /// Make the lights follow the direction of travel, or stop doing so with
+    /// `None`.
+    ///
+    /// Once enabled, the lights matching the current motion are shown, then
+    /// updated by every speed change from any handle.
+    pub async fn set_headlights(
+        &self,
+        headlights: Option<Headlights>,
+    ) -> Result<()> {
+        self.request(|reply| Request::SetHeadlights(headlights, reply))
+            .await
+    }
+
+    /// Run a light effect in the background, showing `frame_rate` frames per
+    /// second at most.
+    ///
+    /// See [`DEFAULT_FRAME_RATE`](crate::DEFAULT_FRAME_RATE) for a sensible
+    /// value, the rate cannot exceed
+    /// [`MAX_FRAME_RATE`](crate::MAX_FRAME_RATE). The effect runs until the
+    /// returned task is cancelled or dropped.
+    pub fn start_effect(
+        &self,
+        effect: Effect,
+        frame_rate: u32,
+    ) -> Result<EffectTask> {
+        EffectTask::spawn(self.clone(), effect, frame_rate)
+    }
+
+    pub async fn set_sound_theme(&self, sound_theme: SoundTheme) -> Result<()> {
+        self.send(Command::SetSoundTheme(sound_theme)).await
+    }
+
+    pub async fn play_sound_effect(&self, effect: SoundEffect) -> Result<()> {
+        self.send(Command::PlaySoundEffect(effect)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        task::JoinSet,
+        time::{Duration, sleep, timeout},
+    };
+
+    use super::*;
+    use crate::{BrioSmartTech, MockTransport, ReconnectPolicy, Transport};
+
+    #[tokio::test]
+    async fn test_concurrent_handles() {
+        let mock = MockTransport::new();
+        let train = BrioSmartTech::with_transport(mock.clone()).await.unwrap();
+        let mut notifications = train.subscribe();
+
+        let mut tasks = JoinSet::new();
+        for speed in 1..=7 {
+            let train = train.clone();
+            tasks.spawn(async move { train.forward(speed).await });
+        }
+        while let Some(result) = tasks.join_next().await {
+            result.unwrap().unwrap();
+        }
+
+        let mut speeds: Vec<u8> =
+            mock.written_frames().iter().map(|frame| frame[3]).collect();
+        speeds.sort();
+        assert_eq!(speeds, (0x02..=0x08).collect::<Vec<u8>>());
+
+        // The connection goes away with the last handle.
+        drop(train);
+        assert_eq!(
+            notifications.recv().await,
+            Err(broadcast::error::RecvError::Closed)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reject_while_reconnecting() {
+        let mock = MockTransport::new();
+        let train = BrioSmartTech::with_reconnect(
+            mock.clone(),
+            ReconnectPolicy {
+                check_interval: Duration::from_millis(10),
+                retry_interval: Duration::from_millis(10),
+                max_attempts: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        mock.set_reachable(false);
+        mock.set_connected(false);
+        sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(
+            *train.connection_state().borrow(),
+            ConnectionState::Disconnected
+        );
+        assert!(matches!(train.stop().await, Err(BrioError::Disconnected)));
+        assert!(!train.is_connected().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_disconnect() {
+        let mock = MockTransport::new();
+        let train = BrioSmartTech::with_transport(mock.clone()).await.unwrap();
+        let other = train.clone();
+        let mut notifications = train.subscribe();
+        let connection_state = train.connection_state();
+
+        train.forward(4).await.unwrap();
+        train.disconnect(true).await.unwrap();
+
+        assert_eq!(
+            mock.take_written_frames(),
+            vec![
+                vec![0xaa, 0x02, 0x01, 0x05, 0xf8],
+                vec![0xaa, 0x02, 0x01, 0x00, 0xfd],
+                vec![0xaa, 0x02, 0x02, 0x00, 0xfc],
+            ]
+        );
+        assert!(!mock.is_connected().await.unwrap());
+        assert_eq!(*connection_state.borrow(), ConnectionState::Disconnected);
+        assert_eq!(
+            notifications.recv().await,
+            Err(broadcast::error::RecvError::Closed)
+        );
+        assert!(matches!(
+            other.forward(1).await,
+            Err(BrioError::Disconnected)
+        ));
+        assert!(mock.written_frames().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stop_on_drop() {
+        let mock = MockTransport::new();
+        let train = BrioSmartTech::with_transport(mock.clone()).await.unwrap();
+        let mut notifications = train.subscribe();
+
+        train.backward(2).await.unwrap();
+        drop(train);
+
+        // Wait for the connection task to be done.
+        assert!(notifications.recv().await.is_err());
+        assert_eq!(
+            mock.written_frames().last(),
+            Some(&vec![0xaa, 0x02, 0x01, 0x00, 0xfd])
+        );
+        assert!(!mock.is_connected().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_ramp() {
+        let mock = MockTransport::new();
+        let train = BrioSmartTech::with_transport(mock.clone()).await.unwrap();
+        let ramp = Ramp::new(Duration::from_millis(40), Default::default());
+
+        train.forward(1).await.unwrap();
+        train
+            .ramp_to(Direction::Backward, Speed::new(1).unwrap(), ramp)
+            .await
+            .unwrap();
+        let speeds: Vec<u8> = mock
+            .take_written_frames()
+            .iter()
+            .map(|frame| frame[3])
+            .collect();
+        assert_eq!(speeds, vec![0x02, 0x00, 0x12]);
+
+        // Stopping cancels the ramp in progress.
+        let ramping = train.clone();
+        let ramp = Ramp::new(Duration::from_secs(7), Default::default());
+        let task = tokio::spawn(async move {
+            ramping.ramp_to(Direction::Forward, Speed::MAX, ramp).await
+        });
+        sleep(Duration::from_millis(50)).await;
+        train.stop().await.unwrap();
+        timeout(Duration::from_secs(1), task)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            mock.take_written_frames(),
+            vec![vec![0xaa, 0x02, 0x01, 0x00, 0xfd]]
+        );
+
+        // So does sending a speed command directly.
+        let ramping = train.clone();
+        let task = tokio::spawn(async move {
+            ramping.ramp_to(Direction::Forward, Speed::MAX, ramp).await
+        });
+        sleep(Duration::from_millis(50)).await;
+        let motion = Motion::new(Direction::Backward, Speed::new(3).unwrap());
+        train.send(Command::SetSpeed(motion)).await.unwrap();
+        timeout(Duration::from_secs(1), task)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(mock.take_written_frames().len(), 1);
+
+        // A step queued by a ramp superseded in the meantime is dropped, even
+        // when it reaches the connection after the stop.
+        let generation = train.supersede_ramp();
+        train.stop().await.unwrap();
+        let step =
+            Command::SetSpeed(Motion::new(Direction::Forward, Speed::MAX));
+        train
+            .request(|reply| Request::Send(step, Some(generation), reply))
+            .await
+            .unwrap();
+        assert_eq!(
+            mock.take_written_frames(),
+            vec![vec![0xaa, 0x02, 0x01, 0x00, 0xfd]]
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Lights following the direction of travel.
+
+use crate::{Color, Direction, Motion};
+
+/// Light color and intensity to show for each direction of travel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Headlights {
+    pub forward: (Color, u8),
+    pub backward: (Color, u8),
+    pub stopped: (Color, u8),
+}
+
+impl Default for Headlights {
+    /// White forward, red reversing, off when stopped.
+    fn default() -> Self {
+        Self {
+            forward: (Color::White, 15),
+            backward: (Color::RedBackward, 15),
+            stopped: (Color::Off, 0),
+        }
+    }
+}
+
+impl Headlights {
+    /// Color and intensity to show while driving with `motion`.
+    pub fn light_for(&self, motion: Motion) -> (Color, u8) {
+        if motion.is_stopped() {
+            return self.stopped;
+        }
+        match motion.direction {
+            Direction::Forward => self.forward,
+            Direction::Backward => self.backward,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BrioSmartTech, Speed, sim::SimTransport};
+
+    #[tokio::test]
+    async fn test_headlights() {
+        let sim = SimTransport::new();
+        let train = BrioSmartTech::with_transport(sim.clone()).await.unwrap();
+
+        // Disabled by default.
+        train.forward(3).await.unwrap();
+        assert_eq!(sim.link().take_written_frames().len(), 1);
+
+        let headlights = Headlights {
+            stopped: (Color::Yellow, 2),
+            ..Headlights::default()
+        };
+        train.set_headlights(Some(headlights)).await.unwrap();
+        assert_eq!(sim.locomotive().color(), (Color::White, 15));
+
+        train.backward(2).await.unwrap();
+        assert_eq!(sim.locomotive().color(), (Color::RedBackward, 15));
+        train.stop().await.unwrap();
+        assert_eq!(sim.locomotive().color(), (Color::Yellow, 2));
+        assert_eq!(train.state().color, Some((Color::Yellow, 2)));
+
+        // The lights are only sent when they change.
+        sim.link().take_written_frames();
+        train.drive(Direction::Backward, Speed::STOP).await.unwrap();
+        assert_eq!(sim.link().take_written_frames().len(), 1);
+
+        train.set_headlights(None).await.unwrap();
+        train.forward(1).await.unwrap();
+        assert_eq!(sim.locomotive().color(), (Color::Yellow, 2));
+    }
+}
@@ -1,22 +1,65 @@
-use std::{error::Error, sync::Arc};
-
-use btleplug::{
-    api::{
-        Central, CharPropFlags, Characteristic, Peripheral as _, ScanFilter,
-        WriteType,
-    },
-    platform::{Adapter, Peripheral},
-};
-use futures::stream::StreamExt;
-use strum::EnumIter;
+// Diagnostics go through `log`, the output streams belong to the application.
+#![deny(clippy::print_stdout, clippy::print_stderr)]
+
+mod builder;
+mod command;
+mod connection;
+mod discovery;
+mod effects;
+mod error;
+pub mod frame;
+mod handle;
+mod headlights;
+mod motion;
+mod notification;
+mod ramp;
+#[cfg(feature = "unstable-raw")]
+mod raw;
+mod reconnect;
+pub mod sim;
+mod sound;
+mod state;
+pub mod transport;
+
+use std::{fmt, future::pending, ops::ControlFlow, str::FromStr, sync::Mutex};
+
+use btleplug::{api::BDAddr, platform::Adapter};
+use futures::stream::{self, StreamExt};
+use strum::{Display, EnumIter, IntoEnumIterator};
 use tokio::{
-    sync::Mutex,
+    sync::{broadcast, mpsc, watch},
     task,
-    time::{Duration, sleep},
+    time::{Interval, interval},
 };
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Copy, EnumIter)]
+pub use crate::{
+    builder::{BrioSmartTechBuilder, DEFAULT_SCAN_TIMEOUT},
+    command::Command,
+    connection::ConnectionState,
+    discovery::{DiscoveredTrain, discover},
+    effects::{DEFAULT_FRAME_RATE, Effect, EffectTask, MAX_FRAME_RATE},
+    error::{BrioError, Result},
+    frame::{Frame, FrameDecoder, FrameError},
+    handle::TrainHandle,
+    headlights::Headlights,
+    motion::{Direction, Motion, Speed},
+    notification::Notification,
+    ramp::{Curve, Ramp},
+    reconnect::ReconnectPolicy,
+    sound::{EffectSlot, SoundEffect, SoundTheme},
+    state::TrainState,
+    transport::{BtleplugTransport, MockTransport, Transport},
+};
+use crate::{
+    handle::Request, reconnect::LastCommands, transport::NotificationStream,
+};
+
+/// Light color, displayed and parsed in kebab case (`light-blue`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+#[strum(serialize_all = "kebab-case")]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum Color {
     Off,
     Yellow,
@@ -33,9 +76,14 @@ pub enum Color {
 }
 
 impl Color {
-    fn get_command_value(&self, intensity: u8) -> u8 {
-        assert!(intensity < 16);
-        (match self {
+    /// Light byte for this color at `intensity`, ranging from 0 to 15.
+    pub fn get_command_value(&self, intensity: u8) -> Result<u8> {
+        if intensity >= 16 {
+            return Err(BrioError::InvalidArgument(format!(
+                "intensity {intensity} is out of range 0..=15"
+            )));
+        }
+        Ok((match self {
             Self::Off => 0,
             Self::Yellow => 1,
             Self::Orange => 2,
@@ -50,7 +98,13 @@ impl Color {
             Self::RedBackward => 11,
         } as u8)
             * 16
-            + intensity
+            + intensity)
+    }
+
+    /// Color and intensity of a light byte, `None` for an unknown color.
+    pub fn from_command_value(value: u8) -> Option<(Self, u8)> {
+        let color = Self::iter().nth(usize::from(value >> 4))?;
+        Some((color, value & 0x0f))
     }
 
     pub fn next(&self) -> Self {
@@ -71,222 +125,418 @@ impl Color {
     }
 }
 
-#[derive(Debug, Clone, Copy, EnumIter)]
-pub enum SoundTheme {
-    Honk,
-    Whistle,
-    Horn,
-    Spaceship,
-}
+impl FromStr for Color {
+    type Err = BrioError;
 
-impl SoundTheme {
-    pub fn get_command_value(&self) -> u8 {
-        0xf0 + match self {
-            Self::Honk => 0,
-            Self::Whistle => 1,
-            Self::Horn => 2,
-            Self::Spaceship => 3,
-        }
+    fn from_str(name: &str) -> Result<Self> {
+        parse_name(name, "color")
     }
+}
 
-    // offsets:
-    // - eff1 0x15
-    // - eff2 0x1d
-    // - eff3 0x11
-    // - eff4 0x19
-    pub fn from_u8(value: u8, offset: u8) -> Option<Self> {
-        match value - offset {
-            0 => Some(Self::Honk),
-            1 => Some(Self::Whistle),
-            2 => Some(Self::Honk),
-            3 => Some(Self::Spaceship),
-            _ => None,
-        }
-    }
+/// Look a variant up by its displayed name, ignoring case and separators.
+pub(crate) fn parse_name<T>(name: &str, kind: &str) -> Result<T>
+where
+    T: IntoEnumIterator + fmt::Display,
+{
+    let normalize = |name: &str| {
+        name.chars()
+            .filter(|c| !matches!(c, '-' | '_' | ' '))
+            .map(|c| c.to_ascii_lowercase())
+            .collect::<String>()
+    };
+    let wanted = normalize(name);
+    T::iter()
+        .find(|variant| normalize(&variant.to_string()) == wanted)
+        .ok_or_else(|| {
+            BrioError::InvalidArgument(format!("unknown {kind} {name:?}"))
+        })
 }
 
-pub struct BrioSmartTech {
-    peripheral: Peripheral,
-    cmd_char: Characteristic,
+/// Number of notifications kept for subscribers lagging behind.
+const NOTIFICATION_CAPACITY: usize = 64;
+
+/// Number of requests queued before handles have to wait.
+const REQUEST_CAPACITY: usize = 32;
+
+/// Connection with a Brio Smart Tech locomotive.
+///
+/// The connection is owned by a background task, the constructors return a
+/// [`TrainHandle`] to control the locomotive.
+pub struct BrioSmartTech<T: Transport = BtleplugTransport> {
+    transport: T,
+    requests: mpsc::Receiver<Request>,
+    notifications: broadcast::Sender<Notification>,
+    connection_state: watch::Sender<ConnectionState>,
+    state: watch::Sender<TrainState>,
+    /// Shared with the reconnection, which applies them again.
+    last_commands: Mutex<LastCommands>,
+    /// Generation of the ramp allowed to change the speed.
+    speed_generation: watch::Receiver<u64>,
+    reconnect: Option<ReconnectPolicy>,
+    headlights: Option<Headlights>,
 }
 
-async fn find_peripheral(central: &Adapter) -> Option<Peripheral> {
-    for p in central.peripherals().await.unwrap() {
-        if p.properties()
-            .await
-            .unwrap()
-            .unwrap()
-            .local_name
-            .iter()
-            .any(|name| name.contains("Smart 2.0"))
-        {
-            return Some(p);
-        }
-    }
-    None
+pub(crate) async fn send_command<T: Transport>(
+    transport: &T,
+    command: Command,
+) -> Result<()> {
+    send_payload(transport, command.to_payload()?).await
 }
 
-fn compute_checksum(payload: &[u8]) -> u8 {
-    let sum: u16 = (payload.len() as u16)
-        + payload.iter().map(|x| u16::from(*x)).sum::<u16>();
-    ((0x100 - (sum & 0xFF)) & 0xFF) as u8
+async fn send_payload<T: Transport>(
+    transport: &T,
+    payload: Vec<u8>,
+) -> Result<()> {
+    transport.write_frame(&Frame::new(payload)?.encode()).await
 }
 
-fn command_data(payload: Vec<u8>) -> Vec<u8> {
-    // Insert first a byte indicating the number of bytes the payload has.
-    // This byte enters in the computation of the checksum.
-    let mut data = vec![0xaa, payload.len().try_into().unwrap()];
-    let checksum = compute_checksum(&payload);
-    data.extend(payload);
-    data.push(checksum);
+/// Speed for driving in a given direction, which cannot be 0.
+pub(crate) fn check_speed(speed: u8) -> Result<Speed> {
+    if speed == 0 || speed > 7 {
+        return Err(BrioError::InvalidArgument(format!(
+            "speed {speed} is out of range 1..=7"
+        )));
+    }
+    Speed::new(speed)
+}
 
-    data
+pub(crate) fn brio_uuid(id: u32) -> Uuid {
+    Uuid::parse_str(&format!("b11b{id:04}-bf9b-4a20-ba07-9218fec577d7"))
+        .unwrap()
 }
 
-async fn notification_watcher(brio_smart_tech: Arc<Mutex<BrioSmartTech>>) {
-    // Print the first 4 notifications received.
-    let mut notification_stream = brio_smart_tech
-        .lock()
-        .await
-        .peripheral
-        .notifications()
-        .await
-        .unwrap();
-    // Process while the BLE connection is not broken or stopped.
-    while let Some(data) = notification_stream.next().await {
-        let mut bytes = data.value.iter();
+impl BrioSmartTech {
+    /// Instantiate the communication with a Brio Smart Tech device.
+    ///
+    /// Connects to the first locomotive found, see [`Self::builder`] to
+    /// select a specific one.
+    // The connection itself is owned by a background task.
+    #[allow(clippy::new_ret_no_self)]
+    pub async fn new(central: &Adapter) -> Result<TrainHandle> {
+        Self::builder().connect(central).await
+    }
 
-        assert_eq!(*bytes.next().unwrap(), 0xaa);
-        let len = *bytes.next().unwrap() as usize;
-        let payload: Vec<u8> = bytes.by_ref().take(len).copied().collect();
-        let checksum = *bytes.next().unwrap();
+    /// Connect to the locomotive with the given Bluetooth address.
+    pub async fn connect_by_address(
+        central: &Adapter,
+        address: BDAddr,
+    ) -> Result<TrainHandle> {
+        Self::builder().address(address).connect(central).await
+    }
 
-        assert_eq!(checksum, compute_checksum(&payload));
-        assert_eq!(bytes.next(), None);
+    /// Connect to the locomotive advertising the given name.
+    pub async fn connect_by_name(
+        central: &Adapter,
+        name: &str,
+    ) -> Result<TrainHandle> {
+        Self::builder().name(name).connect(central).await
+    }
 
-        println!("Data notified {payload:?}");
+    pub fn builder() -> BrioSmartTechBuilder {
+        BrioSmartTechBuilder::new()
     }
 }
 
-fn brio_uuid(id: u32) -> Uuid {
-    Uuid::parse_str(&format!("b11b{id:04}-bf9b-4a20-ba07-9218fec577d7")).unwrap()
+/// Whether a speed change belongs to a ramp superseded since it was queued.
+fn superseded(generation: Option<u64>, current: &watch::Receiver<u64>) -> bool {
+    generation.is_some_and(|generation| generation != *current.borrow())
 }
 
-impl BrioSmartTech {
-    /// Instantiate the communication with a Brio Smart Tech device.
-    pub async fn new(
-        central: &Adapter,
-    ) -> Result<Arc<Mutex<Self>>, Box<dyn Error>> {
-        // service and characteristic have the same uuid for the brio smart 2.0
-        let service_uuid = brio_uuid(1);
-        let control_point_uuid = brio_uuid(2);
-        let notification_uuid = brio_uuid(3);
-
-        println!("Scanning for devices with service UUID: {service_uuid}");
-        central
-            .start_scan(ScanFilter {
-                services: vec![service_uuid],
-            })
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => pending().await,
+    }
+}
+
+/// What woke the connection task up.
+enum Event {
+    Request(Option<Request>),
+    Notification(Option<Vec<u8>>),
+    CheckLink,
+}
+
+impl<T: Transport> BrioSmartTech<T> {
+    /// Instantiate the communication with a device reachable through the
+    /// given transport.
+    pub async fn with_transport(transport: T) -> Result<TrainHandle> {
+        Self::start(transport, None, watch::Sender::new(ConnectionState::Ready))
             .await
-            .unwrap();
+    }
 
-        // Wait a bit to collect some peripherals.
-        sleep(Duration::from_secs(2)).await;
+    /// Same as [`Self::with_transport`], reconnecting according to `policy`
+    /// whenever the link drops.
+    ///
+    /// Once reconnected, the last speed, color and sound theme sent are
+    /// applied again. Requests fail while reconnecting, but the last speed
+    /// requested in the meantime is the one applied.
+    pub async fn with_reconnect(
+        transport: T,
+        policy: ReconnectPolicy,
+    ) -> Result<TrainHandle> {
+        Self::start(
+            transport,
+            Some(policy),
+            watch::Sender::new(ConnectionState::Ready),
+        )
+        .await
+    }
 
-        let peripheral;
+    pub(crate) async fn start(
+        transport: T,
+        reconnect: Option<ReconnectPolicy>,
+        connection_state: watch::Sender<ConnectionState>,
+    ) -> Result<TrainHandle> {
+        let notification_stream = transport.notifications().await?;
+        let (notifications, _) = broadcast::channel(NOTIFICATION_CAPACITY);
+        let (requests, request_receiver) = mpsc::channel(REQUEST_CAPACITY);
+
+        let state = watch::Sender::new(TrainState::default());
+
+        connection_state.send_replace(ConnectionState::Ready);
+        let speed_generation = watch::Sender::new(0);
+        let handle = TrainHandle::new(
+            requests,
+            &notifications,
+            connection_state.subscribe(),
+            state.subscribe(),
+            speed_generation.clone(),
+        );
+
+        let device = Self {
+            transport,
+            requests: request_receiver,
+            notifications,
+            connection_state,
+            state,
+            last_commands: Mutex::default(),
+            speed_generation: speed_generation.subscribe(),
+            reconnect,
+            headlights: None,
+        };
+        task::spawn(device.run(notification_stream));
+
+        Ok(handle)
+    }
+
+    /// Serve the requests and notifications until every handle is dropped.
+    async fn run(mut self, mut notification_stream: NotificationStream) {
+        let mut decoder = FrameDecoder::new();
+        let mut check_link = self
+            .reconnect
+            .as_ref()
+            .map(|policy| interval(policy.check_interval));
 
         loop {
-            if let Some(p) = find_peripheral(central).await {
-                peripheral = p;
-                break;
+            let event = tokio::select! {
+                request = self.requests.recv() => Event::Request(request),
+                data = notification_stream.next() => Event::Notification(data),
+                () = tick(&mut check_link) => Event::CheckLink,
+            };
+
+            let link_lost = match event {
+                Event::Request(Some(request)) => {
+                    if self.serve(request).await.is_break() {
+                        return;
+                    }
+                    false
+                }
+                Event::Request(None) => {
+                    // Every handle is gone, leave the locomotive stopped.
+                    if let Err(err) = self.shutdown(false).await {
+                        log::warn!("Could not stop on drop: {err}");
+                    }
+                    return;
+                }
+                Event::Notification(Some(data)) => {
+                    // Each notification holds whole frames.
+                    decoder.push_complete(&data);
+                    self.dispatch(&mut decoder);
+                    false
+                }
+                Event::Notification(None) => true,
+                Event::CheckLink => {
+                    !self.transport.is_connected().await.unwrap_or(false)
+                }
+            };
+            if !link_lost {
+                continue;
             }
-            sleep(Duration::from_millis(500)).await;
-        }
 
-        central.stop_scan().await?;
-        peripheral.connect().await?;
-        peripheral.discover_services().await?;
-
-        let mut cmd_char: Option<Characteristic> = None;
-
-        for characteristic in peripheral.characteristics() {
-            println!("Characteristic {}", characteristic.uuid);
-            if characteristic.uuid == control_point_uuid {
-                println!("   Control point");
-                cmd_char = Some(characteristic);
-            } else if characteristic.uuid == notification_uuid {
-                println!("   Notification");
-                assert!(
-                    characteristic.properties.contains(CharPropFlags::NOTIFY),
-                    "Unexpected non-notify characteristic"
-                );
-                peripheral.subscribe(&characteristic).await?;
-            } else {
-                continue;
+            self.connection_state
+                .send_replace(ConnectionState::Disconnected);
+            decoder = FrameDecoder::new();
+            match self.restore().await {
+                ControlFlow::Break(()) => return,
+                ControlFlow::Continue(Some(stream)) => {
+                    self.connection_state.send_replace(ConnectionState::Ready);
+                    notification_stream = stream;
+                }
+                ControlFlow::Continue(None) => {
+                    // Keep serving requests, which fail from now on.
+                    notification_stream = Box::pin(stream::pending());
+                    check_link = None;
+                }
             }
         }
-
-        let ret = Arc::new(Mutex::new(Self {
-            peripheral,
-            cmd_char: cmd_char.expect("Could not find command characteristic"),
-        }));
-
-        task::spawn(notification_watcher(ret.clone()));
-
-        Ok(ret)
     }
 
-    pub async fn is_connected(&self) -> Result<bool, btleplug::Error> {
-        self.peripheral.is_connected().await
+    /// Serve a request, breaking once the connection is closed.
+    async fn serve(&mut self, request: Request) -> ControlFlow<()> {
+        // The requester may have given up waiting, nothing to do then.
+        match request {
+            // The ramp was superseded after queueing this step, the speed
+            // change superseding it has been served already.
+            Request::Send(_, generation, reply)
+                if superseded(generation, &self.speed_generation) =>
+            {
+                let _ = reply.send(Ok(()));
+            }
+            Request::Send(command, _, reply) => {
+                let mut result = self.send(command).await;
+                if result.is_ok() && matches!(command, Command::SetSpeed(_)) {
+                    result = self.update_headlights().await;
+                }
+                let _ = reply.send(result);
+            }
+            #[cfg(feature = "unstable-raw")]
+            Request::SendRaw(payload, reply) => {
+                let _ =
+                    reply.send(send_payload(&self.transport, payload).await);
+            }
+            Request::SetHeadlights(headlights, reply) => {
+                self.headlights = headlights;
+                let _ = reply.send(self.update_headlights().await);
+            }
+            Request::IsConnected(reply) => {
+                let _ = reply.send(self.transport.is_connected().await);
+            }
+            Request::Disconnect { lights_off, reply } => {
+                let _ = reply.send(self.shutdown(lights_off).await);
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
     }
 
-    async fn write_command(
-        &self,
-        payload: Vec<u8>,
-    ) -> Result<(), Box<dyn Error>> {
-        self.peripheral
-            .write(
-                &self.cmd_char,
-                &command_data(payload),
-                WriteType::WithoutResponse,
-            )
-            .await?;
+    /// Send a command, keeping track of the state it sets.
+    async fn send(&mut self, command: Command) -> Result<()> {
+        send_command(&self.transport, command).await?;
+        self.state
+            .send_modify(|state| state.record_command(command));
+        self.last_commands.get_mut().unwrap().record(command);
         Ok(())
     }
 
-    pub async fn set_speed(&self, speed: u8) -> Result<(), Box<dyn Error>> {
-        self.write_command(vec![0x01, speed]).await
+    /// Show the lights matching the current motion, if they follow it.
+    async fn update_headlights(&mut self) -> Result<()> {
+        let Some(headlights) = self.headlights else {
+            return Ok(());
+        };
+        let state = self.state.borrow().clone();
+        let (color, intensity) = headlights.light_for(state.motion);
+        if state.color == Some((color, intensity)) {
+            return Ok(());
+        }
+        self.send(Command::SetColor { color, intensity }).await
     }
 
-    pub async fn forward(&self, speed: u8) -> Result<(), Box<dyn Error>> {
-        assert!(speed != 0 && speed <= 7);
-        self.set_speed(speed + 1).await
-    }
+    /// Stop the locomotive and close the link.
+    ///
+    /// The link is closed even if the locomotive could not be stopped, the
+    /// first error being reported.
+    async fn shutdown(&mut self, lights_off: bool) -> Result<()> {
+        let mut result =
+            send_command(&self.transport, Command::SetSpeed(Motion::default()))
+                .await;
+        if lights_off {
+            result = result.and(
+                send_command(
+                    &self.transport,
+                    Command::SetColor {
+                        color: Color::Off,
+                        intensity: 0,
+                    },
+                )
+                .await,
+            );
+        }
+        result = result.and(self.transport.disconnect().await);
 
-    pub async fn backward(&self, speed: u8) -> Result<(), Box<dyn Error>> {
-        assert!(speed != 0 && speed <= 7);
-        self.set_speed(speed + 0x11).await
+        self.connection_state
+            .send_replace(ConnectionState::Disconnected);
+        result
     }
 
-    pub async fn stop(&self) -> Result<(), Box<dyn Error>> {
-        self.set_speed(0).await
+    fn dispatch(&self, decoder: &mut FrameDecoder) {
+        while let Some(frame) = decoder.next_frame() {
+            match frame {
+                Ok(frame) => {
+                    let notification =
+                        Notification::from_payload(frame.payload());
+                    self.state.send_modify(|state| {
+                        state.record_notification(&notification)
+                    });
+                    // Nobody listening is not an error.
+                    let _ = self.notifications.send(notification);
+                }
+                Err(err) => log::warn!("Dropping notification: {err}"),
+            }
+        }
     }
 
-    pub async fn set_color(
-        &self,
-        color: Color,
-        intensity: u8,
-    ) -> Result<(), Box<dyn Error>> {
-        self.write_command(vec![0x02, color.get_command_value(intensity)])
-            .await
-    }
+    /// Reconnect if a policy is set, rejecting the requests received in the
+    /// meantime.
+    ///
+    /// Rejected speed changes are still recorded, so that the locomotive
+    /// comes back following the latest one rather than a stale speed.
+    ///
+    /// A disconnection requested in the meantime cancels the reconnection
+    /// and breaks, the connection being closed.
+    async fn restore(&mut self) -> ControlFlow<(), Option<NotificationStream>> {
+        let Some(policy) = &self.reconnect else {
+            return ControlFlow::Continue(None);
+        };
+        log::warn!("Link lost, reconnecting");
+
+        let (lights_off, reply) = {
+            let restore = reconnect::restore(
+                policy,
+                &self.transport,
+                &self.connection_state,
+                &self.last_commands,
+            );
+            tokio::pin!(restore);
+            let current = &self.speed_generation;
+
+            loop {
+                tokio::select! {
+                    stream = &mut restore => {
+                        return ControlFlow::Continue(stream);
+                    }
+                    request = self.requests.recv() => match request {
+                        Some(Request::Disconnect { lights_off, reply }) => {
+                            break (lights_off, reply);
+                        }
+                        Some(Request::Send(
+                            command @ Command::SetSpeed(_),
+                            generation,
+                            reply,
+                        )) if !superseded(generation, current) => {
+                            self.last_commands.lock().unwrap().record(command);
+                            let _ = reply.send(Err(BrioError::Disconnected));
+                        }
+                        Some(request) => request.reject(),
+                        None => return ControlFlow::Continue(None),
+                    },
+                }
+            }
+        };
 
-    pub async fn set_sound_theme(
-        &self,
-        sound_theme: SoundTheme,
-    ) -> Result<(), Box<dyn Error>> {
-        self.write_command(vec![0x56, 0xaa, sound_theme.get_command_value()])
-            .await
+        let _ = reply.send(self.shutdown(lights_off).await);
+        ControlFlow::Break(())
     }
 }
 
@@ -295,19 +545,39 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_chksum() {
-        assert_eq!(
-            command_data(vec![0x02, Color::Blue.get_command_value(15)]),
-            vec![0xaa, 0x02, 0x02, 0x6f, 0x8d]
-        );
-        assert_eq!(
-            command_data(vec![0x02, Color::LightBlue.get_command_value(15)]),
-            vec![0xaa, 0x02, 0x02, 0x7f, 0x7d]
-        );
+    fn test_invalid_arguments() {
+        assert!(matches!(
+            Color::Red.get_command_value(16),
+            Err(BrioError::InvalidArgument(_))
+        ));
+        assert!(matches!(check_speed(0), Err(BrioError::InvalidArgument(_))));
+        assert!(matches!(check_speed(8), Err(BrioError::InvalidArgument(_))));
+        assert!(check_speed(7).is_ok());
+    }
+
+    #[test]
+    fn test_color() {
+        for color in Color::iter() {
+            for intensity in [0, 15] {
+                let value = color.get_command_value(intensity).unwrap();
+                assert_eq!(
+                    Color::from_command_value(value),
+                    Some((color, intensity))
+                );
+            }
+            assert_eq!(color.to_string().parse::<Color>().unwrap(), color);
+        }
+        assert_eq!(Color::from_command_value(0xc0), None);
 
+        assert_eq!(Color::LightBlue.to_string(), "light-blue");
+        assert_eq!("LightBlue".parse::<Color>().unwrap(), Color::LightBlue);
         assert_eq!(
-            command_data(vec![0x01, 0x00]),
-            vec![0xaa, 0x02, 0x01, 0x00, 0xfd]
+            "red_backward".parse::<Color>().unwrap(),
+            Color::RedBackward
         );
+        assert!(matches!(
+            "magenta".parse::<Color>(),
+            Err(BrioError::InvalidArgument(_))
+        ));
     }
 }
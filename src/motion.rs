@@ -0,0 +1,153 @@
+//! Speed and direction of the locomotive.
+
+use std::fmt;
+
+use crate::{BrioError, Result};
+
+/// Speed of the locomotive, from 0 (stopped) to 7.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "u8", into = "u8"))]
+pub struct Speed(u8);
+
+impl Speed {
+    pub const STOP: Self = Self(0);
+    pub const MAX: Self = Self(7);
+
+    pub fn new(value: u8) -> Result<Self> {
+        if value > Self::MAX.0 {
+            return Err(BrioError::InvalidArgument(format!(
+                "speed {value} is out of range 0..=7"
+            )));
+        }
+        Ok(Self(value))
+    }
+
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for Speed {
+    type Error = BrioError;
+
+    fn try_from(value: u8) -> Result<Self> {
+        Self::new(value)
+    }
+}
+
+impl From<Speed> for u8 {
+    fn from(speed: Speed) -> Self {
+        speed.0
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Direction {
+    #[default]
+    Forward,
+    Backward,
+}
+
+impl Direction {
+    pub fn reversed(self) -> Self {
+        match self {
+            Self::Forward => Self::Backward,
+            Self::Backward => Self::Forward,
+        }
+    }
+}
+
+/// Speed and direction the locomotive is driving at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Motion {
+    pub direction: Direction,
+    pub speed: Speed,
+}
+
+impl Motion {
+    pub fn new(direction: Direction, speed: Speed) -> Self {
+        Self { direction, speed }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.speed == Speed::STOP
+    }
+
+    /// Byte carried by the speed command.
+    ///
+    /// Stopping is always sent as `0x00`, whatever the direction.
+    pub fn to_speed_byte(&self) -> u8 {
+        if self.is_stopped() {
+            return 0x00;
+        }
+        match self.direction {
+            Direction::Forward => self.speed.0 + 0x01,
+            Direction::Backward => self.speed.0 + 0x11,
+        }
+    }
+
+    /// Decode the byte carried by the speed command.
+    ///
+    /// `0x01` and `0x11` are understood as stopped in their direction.
+    pub fn from_speed_byte(value: u8) -> Option<Self> {
+        let direction = match value & 0xf0 {
+            0x00 => Direction::Forward,
+            0x10 => Direction::Backward,
+            _ => return None,
+        };
+        let speed = match value & 0x0f {
+            0x00 if value == 0x00 => Speed::STOP,
+            speed @ 0x01..=0x08 => Speed(speed - 1),
+            _ => return None,
+        };
+        Some(Self { direction, speed })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_byte() {
+        assert!(Speed::new(8).is_err());
+        assert_eq!(Motion::default().to_speed_byte(), 0x00);
+        assert_eq!(
+            Motion::new(Direction::Backward, Speed::STOP).to_speed_byte(),
+            0x00
+        );
+
+        for direction in [Direction::Forward, Direction::Backward] {
+            for speed in 1..=7 {
+                let motion = Motion::new(direction, Speed::new(speed).unwrap());
+                assert_eq!(
+                    Motion::from_speed_byte(motion.to_speed_byte()),
+                    Some(motion)
+                );
+            }
+        }
+        assert_eq!(
+            Motion::new(Direction::Forward, Speed::new(3).unwrap())
+                .to_speed_byte(),
+            0x04
+        );
+        assert_eq!(Motion::from_speed_byte(0x00), Some(Motion::default()));
+        assert_eq!(
+            Motion::from_speed_byte(0x11),
+            Some(Motion::new(Direction::Backward, Speed::STOP))
+        );
+        for value in [0x09, 0x10, 0x19, 0x20, 0xff] {
+            assert_eq!(Motion::from_speed_byte(value), None);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Events reported by the locomotive.
+
+use crate::{Color, Command, Motion, Result, SoundEffect, SoundTheme};
+
+/// A notification, decoded from the payload of a frame.
+///
+/// State reports share the layout of the command setting that state.
+/// Payloads that do not match any known layout are kept as
+/// [`Notification::Unknown`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Notification {
+    /// Speed and direction the locomotive is driving at.
+    Speed(Motion),
+    /// Current light color, `intensity` ranging from 0 to 15.
+    Light { color: Color, intensity: u8 },
+    /// Set of sounds currently selected.
+    SoundTheme(SoundTheme),
+    /// A sound effect was played.
+    SoundEffect(SoundEffect),
+    /// Payload not matching any known layout.
+    ///
+    /// Track tag detections end up here until their layout is documented.
+    Unknown(Vec<u8>),
+}
+
+impl Notification {
+    pub fn from_payload(payload: &[u8]) -> Self {
+        match Command::from_payload(payload) {
+            Ok(Command::SetSpeed(speed)) => return Self::Speed(speed),
+            Ok(Command::SetColor { color, intensity }) => {
+                return Self::Light { color, intensity };
+            }
+            Ok(Command::SetSoundTheme(sound_theme)) => {
+                return Self::SoundTheme(sound_theme);
+            }
+            Ok(Command::PlaySoundEffect(effect)) => {
+                return Self::SoundEffect(effect);
+            }
+            Err(_) => {}
+        }
+        Self::Unknown(payload.to_vec())
+    }
+
+    pub fn to_payload(&self) -> Result<Vec<u8>> {
+        let command = match self {
+            Self::Speed(speed) => Command::SetSpeed(*speed),
+            Self::Light { color, intensity } => Command::SetColor {
+                color: *color,
+                intensity: *intensity,
+            },
+            Self::SoundTheme(sound_theme) => {
+                Command::SetSoundTheme(*sound_theme)
+            }
+            Self::SoundEffect(effect) => Command::PlaySoundEffect(*effect),
+            Self::Unknown(payload) => return Ok(payload.clone()),
+        };
+        command.to_payload()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Direction, EffectSlot, Speed};
+
+    #[test]
+    fn test_from_payload() {
+        assert_eq!(
+            Notification::from_payload(&[0x01, 0x12]),
+            Notification::Speed(Motion::new(
+                Direction::Backward,
+                Speed::new(1).unwrap()
+            ))
+        );
+        assert_eq!(
+            Notification::from_payload(&[0x02, 0xa3]),
+            Notification::Light {
+                color: Color::White,
+                intensity: 3
+            }
+        );
+        assert_eq!(
+            Notification::from_payload(&[0x56, 0xaa, 0xf1]),
+            Notification::SoundTheme(SoundTheme::Whistle)
+        );
+        assert_eq!(
+            Notification::from_payload(&[0x56, 0xaa, 0x15]),
+            Notification::SoundEffect(SoundEffect::new(
+                SoundTheme::Honk,
+                EffectSlot::Effect1
+            ))
+        );
+        assert_eq!(
+            Notification::from_payload(&[0x56, 0xaa, 0x42]),
+            Notification::Unknown(vec![0x56, 0xaa, 0x42])
+        );
+    }
+
+    #[test]
+    fn test_to_payload() {
+        for payload in [
+            &[0x01, 0x12][..],
+            &[0x02, 0xa3],
+            &[0x56, 0xaa, 0xf1],
+            &[0x56, 0xaa, 0x15],
+            &[0x03, 0x07],
+            &[0x42],
+        ] {
+            assert_eq!(
+                Notification::from_payload(payload).to_payload().unwrap(),
+                payload
+            );
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Progressive speed changes.
+
+use tokio::time::Duration;
+
+use crate::{Direction, Motion, Speed};
+
+/// Shape of the speed change over time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Curve {
+    /// Constant acceleration.
+    #[default]
+    Linear,
+    /// Slow start, fast end.
+    EaseIn,
+    /// Fast start, slow end.
+    EaseOut,
+    /// Slow start and end.
+    EaseInOut,
+}
+
+impl Curve {
+    /// Fraction of the ramp duration at which `progress` (between 0 and 1)
+    /// of the speed change is reached.
+    fn time_at(self, progress: f64) -> f64 {
+        match self {
+            Self::Linear => progress,
+            Self::EaseIn => progress.sqrt(),
+            Self::EaseOut => 1.0 - (1.0 - progress).sqrt(),
+            Self::EaseInOut if progress < 0.5 => (progress / 2.0).sqrt(),
+            Self::EaseInOut => 1.0 - ((1.0 - progress) / 2.0).sqrt(),
+        }
+    }
+}
+
+/// How to reach a new speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Ramp {
+    /// Time taken by the whole speed change.
+    pub duration: Duration,
+    pub curve: Curve,
+}
+
+impl Ramp {
+    pub fn new(duration: Duration, curve: Curve) -> Self {
+        Self { duration, curve }
+    }
+}
+
+/// Speed counted positive forward and negative backward.
+fn velocity(motion: Motion) -> i8 {
+    let speed = motion.speed.get() as i8;
+    match motion.direction {
+        Direction::Forward => speed,
+        Direction::Backward => -speed,
+    }
+}
+
+fn motion(velocity: i8) -> Motion {
+    let direction = if velocity < 0 {
+        Direction::Backward
+    } else {
+        Direction::Forward
+    };
+    // At most 7 in absolute value, as built from speeds.
+    Motion::new(direction, Speed::new(velocity.unsigned_abs()).unwrap())
+}
+
+/// Every speed to go through from `from` to `to`, with the time at which to
+/// apply it, counted from the start of the ramp.
+///
+/// Reversing goes through a stop. The last step is always `to`.
+pub(crate) fn plan(
+    from: Motion,
+    to: Motion,
+    ramp: Ramp,
+) -> Vec<(Duration, Motion)> {
+    let (start, end) = (velocity(from), velocity(to));
+    let count = start.abs_diff(end);
+    if count == 0 {
+        return vec![(Duration::ZERO, to)];
+    }
+
+    let step = (end - start).signum();
+    (1..=count)
+        .map(|index| {
+            let progress = f64::from(index) / f64::from(count);
+            let at = ramp.duration.mul_f64(ramp.curve.time_at(progress));
+            let motion = if index == count {
+                to
+            } else {
+                motion(start + step * index as i8)
+            };
+            (at, motion)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forward(speed: u8) -> Motion {
+        Motion::new(Direction::Forward, Speed::new(speed).unwrap())
+    }
+
+    fn backward(speed: u8) -> Motion {
+        Motion::new(Direction::Backward, Speed::new(speed).unwrap())
+    }
+
+    #[test]
+    fn test_plan_linear() {
+        let ramp = Ramp::new(Duration::from_secs(4), Curve::Linear);
+
+        assert_eq!(
+            plan(forward(0), forward(4), ramp),
+            vec![
+                (Duration::from_secs(1), forward(1)),
+                (Duration::from_secs(2), forward(2)),
+                (Duration::from_secs(3), forward(3)),
+                (Duration::from_secs(4), forward(4)),
+            ]
+        );
+        assert_eq!(
+            plan(forward(2), backward(2), ramp),
+            vec![
+                (Duration::from_secs(1), forward(1)),
+                (Duration::from_secs(2), forward(0)),
+                (Duration::from_secs(3), backward(1)),
+                (Duration::from_secs(4), backward(2)),
+            ]
+        );
+        assert_eq!(
+            plan(forward(3), forward(3), ramp),
+            vec![(Duration::ZERO, forward(3))]
+        );
+    }
+
+    #[test]
+    fn test_curves() {
+        for curve in [
+            Curve::Linear,
+            Curve::EaseIn,
+            Curve::EaseOut,
+            Curve::EaseInOut,
+        ] {
+            let steps = plan(
+                backward(7),
+                forward(7),
+                Ramp::new(Duration::from_secs(14), curve),
+            );
+            assert_eq!(steps.len(), 14);
+            assert!(steps.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            assert_eq!(
+                steps.last(),
+                Some(&(Duration::from_secs(14), forward(7)))
+            );
+        }
+
+        // Easing in spends more time on the first steps.
+        let ramp = Ramp::new(Duration::from_secs(4), Curve::EaseIn);
+        assert_eq!(
+            plan(forward(0), forward(4), ramp)[0].0,
+            Duration::from_secs(2)
+        );
+        let ramp = Ramp::new(Duration::from_secs(4), Curve::EaseOut);
+        assert!(
+            plan(forward(0), forward(4), ramp)[0].0 < Duration::from_secs(1)
+        );
+    }
+}
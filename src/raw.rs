@@ -0,0 +1,98 @@
+//! Sending payloads the library does not know about, to explore the
+//! protocol.
+
+use tokio::{
+    sync::broadcast::error::RecvError,
+    time::{Duration, timeout},
+};
+
+use crate::{BrioError, Notification, Result, TrainHandle, handle::Request};
+
+impl TrainHandle {
+    /// Send an arbitrary payload, framed like the commands.
+    ///
+    /// The payload is not checked. It does not update
+    /// [`TrainHandle::state`], nor is it applied again when reconnecting.
+    pub async fn send_raw(&self, payload: &[u8]) -> Result<()> {
+        let payload = payload.to_vec();
+        self.request(|reply| Request::SendRaw(payload, reply)).await
+    }
+
+    /// Send an arbitrary payload with [`TrainHandle::send_raw`], then wait
+    /// for the next notification.
+    ///
+    /// Fails with [`BrioError::Timeout`] when nothing is notified within
+    /// `wait`.
+    pub async fn request_raw(
+        &self,
+        payload: &[u8],
+        wait: Duration,
+    ) -> Result<Notification> {
+        // Subscribe first, not to miss a quick answer.
+        let mut notifications = self.subscribe();
+        self.send_raw(payload).await?;
+
+        timeout(wait, async {
+            loop {
+                match notifications.recv().await {
+                    Ok(notification) => return Ok(notification),
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => {
+                        return Err(BrioError::Disconnected);
+                    }
+                }
+            }
+        })
+        .await
+        .map_err(|_| BrioError::Timeout)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BrioSmartTech, MockTransport, sim::SimTransport};
+
+    #[tokio::test]
+    async fn test_send_raw() {
+        let mock = MockTransport::new();
+        let train = BrioSmartTech::with_transport(mock.clone()).await.unwrap();
+
+        // Undocumented opcodes are framed and sent as is.
+        train.send_raw(&[0x42, 0x01]).await.unwrap();
+        assert_eq!(
+            mock.take_written_frames(),
+            vec![vec![0xaa, 0x02, 0x42, 0x01, 0xbb]]
+        );
+
+        mock.respond_to(
+            [0xaa, 0x01, 0x07, 0xf8],
+            [vec![0xaa, 0x02, 0x07, 0x33, 0xc4]],
+        );
+        assert_eq!(
+            train
+                .request_raw(&[0x07], Duration::from_secs(1))
+                .await
+                .unwrap(),
+            Notification::Unknown(vec![0x07, 0x33])
+        );
+        assert!(matches!(
+            train.request_raw(&[0x08], Duration::from_millis(20)).await,
+            Err(BrioError::Timeout)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_request_known_command() {
+        let sim = SimTransport::new();
+        let train = BrioSmartTech::with_transport(sim.clone()).await.unwrap();
+
+        assert_eq!(
+            train
+                .request_raw(&[0x56, 0xaa, 0xf3], Duration::from_secs(1))
+                .await
+                .unwrap(),
+            Notification::SoundTheme(crate::SoundTheme::Spaceship)
+        );
+    }
+}
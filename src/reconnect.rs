@@ -0,0 +1,260 @@
+//! Restoring the link with the locomotive when it drops.
+
+use std::sync::Mutex;
+
+use tokio::{
+    sync::watch,
+    time::{Duration, sleep},
+};
+
+use crate::{
+    Command, ConnectionState, Transport, send_command,
+    transport::NotificationStream,
+};
+
+/// How a dropped link is restored.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Interval at which the link is checked.
+    pub check_interval: Duration,
+    /// Delay between two reconnection attempts.
+    pub retry_interval: Duration,
+    /// Give up after this many failed attempts, `None` to retry forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(1),
+            retry_interval: Duration::from_secs(1),
+            max_attempts: None,
+        }
+    }
+}
+
+/// Last commands sent for each part of the locomotive state.
+#[derive(Debug, Default)]
+pub(crate) struct LastCommands {
+    speed: Option<Command>,
+    color: Option<Command>,
+    sound_theme: Option<Command>,
+}
+
+impl LastCommands {
+    pub(crate) fn record(&mut self, command: Command) {
+        match command {
+            Command::SetSpeed(_) => self.speed = Some(command),
+            Command::SetColor { .. } => self.color = Some(command),
+            Command::SetSoundTheme(_) => self.sound_theme = Some(command),
+            Command::PlaySoundEffect(_) => {}
+        }
+    }
+
+    /// Commands bringing a fresh locomotive back to the recorded state,
+    /// setting the speed last.
+    fn commands(&self) -> Vec<Command> {
+        [self.sound_theme, self.color, self.speed]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+/// Reconnect and re-apply the last known state, returning the new
+/// notification stream, or `None` when giving up.
+pub(crate) async fn restore<T: Transport>(
+    policy: &ReconnectPolicy,
+    transport: &T,
+    connection_state: &watch::Sender<ConnectionState>,
+    last_commands: &Mutex<LastCommands>,
+) -> Option<NotificationStream> {
+    let mut attempts = 0;
+
+    loop {
+        if policy.max_attempts.is_some_and(|max| attempts >= max) {
+            log::warn!("Giving up reconnecting after {attempts} attempts");
+            return None;
+        }
+        attempts += 1;
+
+        connection_state.send_replace(ConnectionState::Reconnecting);
+        match try_restore(transport, last_commands).await {
+            Ok(stream) => return Some(stream),
+            Err(err) => log::info!("Reconnection attempt failed: {err}"),
+        }
+        connection_state.send_replace(ConnectionState::Disconnected);
+        sleep(policy.retry_interval).await;
+    }
+}
+
+async fn try_restore<T: Transport>(
+    transport: &T,
+    last_commands: &Mutex<LastCommands>,
+) -> crate::Result<NotificationStream> {
+    transport.reconnect().await?;
+    let stream = transport.notifications().await?;
+
+    loop {
+        let commands = last_commands.lock().unwrap().commands();
+        for &command in &commands {
+            send_command(transport, command).await?;
+        }
+        // The speed may have changed while sending, apply it too.
+        if last_commands.lock().unwrap().commands() == commands {
+            return Ok(stream);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::timeout;
+
+    use super::*;
+    use crate::{
+        BrioSmartTech, Color, MockTransport, SoundTheme, sim::SimTransport,
+    };
+
+    fn policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            check_interval: Duration::from_millis(10),
+            retry_interval: Duration::from_millis(10),
+            max_attempts: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restore_state() {
+        let sim = SimTransport::new();
+        let train = BrioSmartTech::with_reconnect(sim.clone(), policy())
+            .await
+            .unwrap();
+        let mut connection_state = train.connection_state();
+        assert_eq!(*connection_state.borrow(), ConnectionState::Ready);
+
+        train.set_sound_theme(SoundTheme::Spaceship).await.unwrap();
+        train.set_color(Color::Red, 4).await.unwrap();
+        train.forward(2).await.unwrap();
+        train.forward(6).await.unwrap();
+
+        sim.power_cycle();
+        assert!(sim.locomotive().motion().is_stopped());
+
+        timeout(Duration::from_secs(1), async {
+            while sim.locomotive().motion().speed.get() != 6 {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let locomotive = sim.locomotive();
+        assert_eq!(locomotive.color(), (Color::Red, 4));
+        assert_eq!(locomotive.sound_theme(), SoundTheme::Spaceship);
+        assert!(train.is_connected().await.unwrap());
+        assert_eq!(
+            *connection_state.borrow_and_update(),
+            ConnectionState::Ready
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_while_reconnecting() {
+        let mock = MockTransport::new();
+        let train = BrioSmartTech::with_reconnect(mock.clone(), policy())
+            .await
+            .unwrap();
+        train.forward(5).await.unwrap();
+
+        mock.set_reachable(false);
+        mock.set_connected(false);
+        let mut connection_state = train.connection_state();
+        let disconnected = connection_state
+            .wait_for(|state| *state == ConnectionState::Disconnected);
+        timeout(Duration::from_secs(1), disconnected)
+            .await
+            .unwrap()
+            .unwrap();
+        mock.take_written_frames();
+        // Stopping cannot reach the locomotive while the link is down, only
+        // the outcome matters.
+        let _ = train.disconnect(true).await;
+
+        // The locomotive coming back is not reconnected, let alone restarted.
+        mock.set_reachable(true);
+        sleep(Duration::from_millis(100)).await;
+        assert!(!mock.is_connected().await.unwrap());
+        assert!(mock.written_frames().is_empty());
+        assert_eq!(
+            *train.connection_state().borrow(),
+            ConnectionState::Disconnected
+        );
+        assert!(matches!(
+            train.is_connected().await,
+            Err(crate::BrioError::Disconnected)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stop_while_reconnecting() {
+        let mock = MockTransport::new();
+        let train = BrioSmartTech::with_reconnect(mock.clone(), policy())
+            .await
+            .unwrap();
+        train.forward(5).await.unwrap();
+
+        mock.set_reachable(false);
+        mock.set_connected(false);
+        let mut connection_state = train.connection_state();
+        let disconnected = connection_state
+            .wait_for(|state| *state == ConnectionState::Disconnected);
+        timeout(Duration::from_secs(1), disconnected)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            train.stop().await,
+            Err(crate::BrioError::Disconnected)
+        ));
+        mock.take_written_frames();
+
+        // The locomotive comes back stopped, not at the speed before.
+        mock.set_reachable(true);
+        let ready =
+            connection_state.wait_for(|state| *state == ConnectionState::Ready);
+        timeout(Duration::from_secs(1), ready)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            mock.written_frames(),
+            vec![vec![0xaa, 0x02, 0x01, 0x00, 0xfd]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_give_up() {
+        let mock = MockTransport::new();
+        let train = BrioSmartTech::with_reconnect(
+            mock.clone(),
+            ReconnectPolicy {
+                max_attempts: Some(2),
+                ..policy()
+            },
+        )
+        .await
+        .unwrap();
+
+        mock.set_reachable(false);
+        mock.set_connected(false);
+        sleep(Duration::from_millis(100)).await;
+        mock.set_reachable(true);
+        sleep(Duration::from_millis(100)).await;
+
+        assert!(!train.is_connected().await.unwrap());
+        assert_eq!(
+            *train.connection_state().borrow(),
+            ConnectionState::Disconnected
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Software model of a Smart 2.0 locomotive.
+//!
+//! [`Locomotive`] implements the device side of the protocol: it decodes the
+//! command frames sent by the library, tracks the resulting state and answers
+//! with notification frames. [`SimTransport`] plugs it behind a
+//! [`Transport`], so a [`BrioSmartTech`](crate::BrioSmartTech) can be driven
+//! without any hardware.
+//!
+//! The model acknowledges every accepted command by notifying its payload
+//! back, reporting the state the locomotive is now in.
+
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    Color, Command, Frame, MockTransport, Motion, Notification, Result,
+    SoundEffect, SoundTheme,
+    transport::{NotificationStream, Transport},
+};
+
+/// State machine of a locomotive.
+#[derive(Debug, Clone)]
+pub struct Locomotive {
+    motion: Motion,
+    color: Color,
+    intensity: u8,
+    sound_theme: SoundTheme,
+    last_sound_effect: Option<SoundEffect>,
+}
+
+impl Locomotive {
+    pub fn new() -> Self {
+        Self {
+            motion: Motion::default(),
+            color: Color::Off,
+            intensity: 0,
+            sound_theme: SoundTheme::Honk,
+            last_sound_effect: None,
+        }
+    }
+
+    /// Current speed and direction.
+    pub fn motion(&self) -> Motion {
+        self.motion
+    }
+
+    /// Current light color and intensity.
+    pub fn color(&self) -> (Color, u8) {
+        (self.color, self.intensity)
+    }
+
+    pub fn sound_theme(&self) -> SoundTheme {
+        self.sound_theme
+    }
+
+    /// Sound effect played last, if any.
+    pub fn last_sound_effect(&self) -> Option<SoundEffect> {
+        self.last_sound_effect
+    }
+
+    /// Process a command frame, returning the notification frames the
+    /// locomotive answers with.
+    pub fn handle_frame(&mut self, frame: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let frame = Frame::decode(frame)?;
+        self.handle_payload(frame.payload())?;
+
+        Ok(vec![frame.encode()])
+    }
+
+    /// Apply a command payload to the state.
+    fn handle_payload(&mut self, payload: &[u8]) -> Result<()> {
+        match Command::from_payload(payload)? {
+            Command::SetSpeed(motion) => self.motion = motion,
+            Command::SetColor { color, intensity } => {
+                self.color = color;
+                self.intensity = intensity;
+            }
+            Command::SetSoundTheme(sound_theme) => {
+                self.sound_theme = sound_theme;
+            }
+            Command::PlaySoundEffect(effect) => {
+                self.last_sound_effect = Some(effect);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Locomotive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Transport connected to a simulated [`Locomotive`].
+///
+/// Frames the locomotive rejects are silently ignored, as the real device
+/// does. Clones share the same locomotive and link.
+#[derive(Clone, Default)]
+pub struct SimTransport {
+    locomotive: Arc<Mutex<Locomotive>>,
+    link: MockTransport,
+}
+
+impl SimTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot of the simulated locomotive.
+    pub fn locomotive(&self) -> Locomotive {
+        self.locomotive.lock().unwrap().clone()
+    }
+
+    /// Make the locomotive report an event.
+    pub fn emit(&self, notification: &Notification) -> Result<()> {
+        self.link
+            .inject(Frame::new(notification.to_payload()?)?.encode());
+        Ok(())
+    }
+
+    /// Simulate the locomotive being switched off and on: its state is reset
+    /// and the link goes down.
+    pub fn power_cycle(&self) {
+        *self.locomotive.lock().unwrap() = Locomotive::new();
+        self.link.set_connected(false);
+    }
+
+    /// Underlying link, recording frames and allowing to inject
+    /// notifications or connection drops.
+    pub fn link(&self) -> &MockTransport {
+        &self.link
+    }
+}
+
+impl Transport for SimTransport {
+    async fn write_frame(&self, frame: &[u8]) -> Result<()> {
+        self.link.write_frame(frame).await?;
+
+        let notifications = self.locomotive.lock().unwrap().handle_frame(frame);
+        for notification in notifications.unwrap_or_default() {
+            self.link.inject(notification);
+        }
+        Ok(())
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream> {
+        self.link.notifications().await
+    }
+
+    async fn is_connected(&self) -> Result<bool> {
+        self.link.is_connected().await
+    }
+
+    async fn reconnect(&self) -> Result<()> {
+        self.link.reconnect().await
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        self.link.disconnect().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::{BrioSmartTech, Direction, EffectSlot, Speed};
+
+    #[test]
+    fn test_locomotive() {
+        let mut locomotive = Locomotive::new();
+
+        assert_eq!(
+            locomotive
+                .handle_frame(&[0xaa, 0x02, 0x01, 0x18, 0xe5])
+                .unwrap(),
+            vec![vec![0xaa, 0x02, 0x01, 0x18, 0xe5]]
+        );
+        assert_eq!(
+            locomotive.motion(),
+            Motion::new(Direction::Backward, Speed::MAX)
+        );
+
+        locomotive
+            .handle_frame(&[0xaa, 0x02, 0x02, 0x6f, 0x8d])
+            .unwrap();
+        assert_eq!(locomotive.color(), (Color::Blue, 15));
+
+        // Corrupted frames and unknown commands leave the state untouched.
+        assert!(locomotive.handle_frame(&[0xaa, 0x02, 0x01, 0x00]).is_err());
+        assert!(
+            locomotive
+                .handle_frame(&Frame::new([0x01, 0x42]).unwrap().encode())
+                .is_err()
+        );
+        assert!(
+            locomotive
+                .handle_frame(&Frame::new([0x02, 0xc0]).unwrap().encode())
+                .is_err()
+        );
+        assert_eq!(locomotive.motion().speed, Speed::MAX);
+        assert_eq!(locomotive.color(), (Color::Blue, 15));
+    }
+
+    #[tokio::test]
+    async fn test_sim_transport() {
+        let sim = SimTransport::new();
+        let mut notifications = sim.notifications().await.unwrap();
+        let train = BrioSmartTech::with_transport(sim.clone()).await.unwrap();
+
+        train.forward(5).await.unwrap();
+        train.set_color(Color::Green, 8).await.unwrap();
+        train.set_sound_theme(SoundTheme::Whistle).await.unwrap();
+
+        let locomotive = sim.locomotive();
+        assert_eq!(
+            locomotive.motion(),
+            Motion::new(Direction::Forward, Speed::new(5).unwrap())
+        );
+        assert_eq!(locomotive.color(), (Color::Green, 8));
+        assert_eq!(locomotive.sound_theme(), SoundTheme::Whistle);
+
+        assert_eq!(
+            notifications.next().await,
+            Some(vec![0xaa, 0x02, 0x01, 0x06, 0xf7])
+        );
+        assert_eq!(sim.link().written_frames().len(), 3);
+
+        train.stop().await.unwrap();
+        assert!(sim.locomotive().motion().is_stopped());
+
+        let effect = SoundEffect::new(SoundTheme::Horn, EffectSlot::Effect3);
+        train.play_sound_effect(effect).await.unwrap();
+        assert_eq!(sim.locomotive().last_sound_effect(), Some(effect));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let sim = SimTransport::new();
+        let train = BrioSmartTech::with_transport(sim.clone()).await.unwrap();
+        let mut first = train.subscribe();
+        let mut second = train.subscribe();
+
+        train.backward(1).await.unwrap();
+        let unknown = Notification::Unknown(vec![0x03, 0x07]);
+        sim.emit(&unknown).unwrap();
+
+        for receiver in [&mut first, &mut second] {
+            assert_eq!(
+                receiver.recv().await,
+                Ok(Notification::Speed(Motion::new(
+                    Direction::Backward,
+                    Speed::new(1).unwrap()
+                )))
+            );
+            assert_eq!(receiver.recv().await, Ok(unknown.clone()));
+        }
+
+        // Notifications have been accounted for before being broadcast.
+        let state = train.state();
+        assert_eq!(state.motion.direction, Direction::Backward);
+        assert_eq!(state.last_notification, Some(unknown));
+        assert!(state.last_notification_at >= state.last_command_at);
+    }
+}
@@ -0,0 +1,142 @@
+//! Sounds played by the locomotive.
+
+use std::str::FromStr;
+
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+use crate::{BrioError, Result, parse_name};
+
+/// Set of sounds, displayed and parsed in kebab case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+#[strum(serialize_all = "kebab-case")]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum SoundTheme {
+    Honk,
+    Whistle,
+    Horn,
+    Spaceship,
+}
+
+impl SoundTheme {
+    pub fn get_command_value(&self) -> u8 {
+        0xf0 + self.index()
+    }
+
+    pub fn from_command_value(value: u8) -> Option<Self> {
+        Self::iter().nth(usize::from(value.checked_sub(0xf0)?))
+    }
+
+    /// Theme of a sound effect byte, relative to the offset of the effect
+    /// slot (see [`EffectSlot::offset`]).
+    pub fn from_u8(value: u8, offset: u8) -> Option<Self> {
+        match value.checked_sub(offset)? {
+            0 => Some(Self::Honk),
+            1 => Some(Self::Whistle),
+            2 => Some(Self::Horn),
+            3 => Some(Self::Spaceship),
+            _ => None,
+        }
+    }
+
+    fn index(&self) -> u8 {
+        match self {
+            Self::Honk => 0,
+            Self::Whistle => 1,
+            Self::Horn => 2,
+            Self::Spaceship => 3,
+        }
+    }
+}
+
+impl FromStr for SoundTheme {
+    type Err = BrioError;
+
+    fn from_str(name: &str) -> Result<Self> {
+        parse_name(name, "sound theme")
+    }
+}
+
+/// Each theme provides four sound effects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum EffectSlot {
+    Effect1,
+    Effect2,
+    Effect3,
+    Effect4,
+}
+
+impl EffectSlot {
+    /// Value of the sound effect byte for the first theme, the following
+    /// themes using the next values.
+    pub fn offset(&self) -> u8 {
+        match self {
+            Self::Effect1 => 0x15,
+            Self::Effect2 => 0x1d,
+            Self::Effect3 => 0x11,
+            Self::Effect4 => 0x19,
+        }
+    }
+}
+
+/// A sound effect, one of the slots of a theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SoundEffect {
+    pub theme: SoundTheme,
+    pub slot: EffectSlot,
+}
+
+impl SoundEffect {
+    pub fn new(theme: SoundTheme, slot: EffectSlot) -> Self {
+        Self { theme, slot }
+    }
+
+    pub fn get_command_value(&self) -> u8 {
+        self.slot.offset() + self.theme.index()
+    }
+
+    /// Decode a sound effect byte.
+    pub fn from_command_value(value: u8) -> Option<Self> {
+        EffectSlot::iter().find_map(|slot| {
+            SoundTheme::from_u8(value, slot.offset())
+                .map(|theme| Self::new(theme, slot))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sound_effect() {
+        assert_eq!(SoundTheme::from_u8(0x10, 0x11), None);
+        assert_eq!(SoundTheme::from_u8(0x13, 0x11), Some(SoundTheme::Horn));
+
+        let mut values: Vec<u8> = SoundTheme::iter()
+            .flat_map(|theme| {
+                EffectSlot::iter()
+                    .map(move |slot| SoundEffect::new(theme, slot))
+            })
+            .map(|effect| {
+                assert_eq!(
+                    SoundEffect::from_command_value(effect.get_command_value()),
+                    Some(effect)
+                );
+                effect.get_command_value()
+            })
+            .collect();
+        values.sort();
+        assert_eq!(values, (0x11..=0x20).collect::<Vec<u8>>());
+
+        assert_eq!(SoundEffect::from_command_value(0x10), None);
+        assert_eq!(SoundEffect::from_command_value(0x21), None);
+        assert_eq!(
+            SoundEffect::from_command_value(0x1f),
+            Some(SoundEffect::new(SoundTheme::Horn, EffectSlot::Effect2))
+        );
+    }
+}
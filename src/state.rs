@@ -0,0 +1,94 @@
+//! Last known state of the locomotive.
+
+use std::time::Instant;
+
+use crate::{Color, Command, Motion, Notification, SoundTheme};
+
+/// Snapshot of what is known about the locomotive, from the commands sent
+/// and the notifications received.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TrainState {
+    /// Speed and direction, stopped until told otherwise.
+    pub motion: Motion,
+    /// Light color and intensity, `None` until set or reported.
+    pub color: Option<(Color, u8)>,
+    /// Set of sounds, `None` until set or reported.
+    pub sound_theme: Option<SoundTheme>,
+    /// Last notification decoded.
+    pub last_notification: Option<Notification>,
+    /// When the last command was sent successfully.
+    pub last_command_at: Option<Instant>,
+    /// When the last notification was decoded.
+    pub last_notification_at: Option<Instant>,
+}
+
+impl TrainState {
+    /// Whether the lights are known to be on.
+    pub fn lights_on(&self) -> bool {
+        self.color.is_some_and(|(color, intensity)| {
+            color != Color::Off && intensity > 0
+        })
+    }
+
+    /// Account for a command the device accepted.
+    pub(crate) fn record_command(&mut self, command: Command) {
+        match command {
+            Command::SetSpeed(motion) => self.motion = motion,
+            Command::SetColor { color, intensity } => {
+                self.color = Some((color, intensity));
+            }
+            Command::SetSoundTheme(sound_theme) => {
+                self.sound_theme = Some(sound_theme);
+            }
+            Command::PlaySoundEffect(_) => {}
+        }
+        self.last_command_at = Some(Instant::now());
+    }
+
+    /// Account for a state report from the device.
+    pub(crate) fn record_notification(&mut self, notification: &Notification) {
+        match *notification {
+            Notification::Speed(motion) => self.motion = motion,
+            Notification::Light { color, intensity } => {
+                self.color = Some((color, intensity));
+            }
+            Notification::SoundTheme(sound_theme) => {
+                self.sound_theme = Some(sound_theme);
+            }
+            Notification::SoundEffect(_) | Notification::Unknown(_) => {}
+        }
+        self.last_notification = Some(notification.clone());
+        self.last_notification_at = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Direction, Speed};
+
+    #[test]
+    fn test_record() {
+        let mut state = TrainState::default();
+        assert!(!state.lights_on());
+
+        let motion = Motion::new(Direction::Backward, Speed::new(3).unwrap());
+        state.record_command(Command::SetSpeed(motion));
+        state.record_command(Command::SetColor {
+            color: Color::Green,
+            intensity: 7,
+        });
+        assert_eq!(state.motion, motion);
+        assert!(state.lights_on());
+        assert!(state.last_command_at.is_some());
+        assert_eq!(state.last_notification_at, None);
+
+        let notification = Notification::SoundTheme(SoundTheme::Horn);
+        state.record_notification(&notification);
+        let unknown = Notification::Unknown(vec![0x42]);
+        state.record_notification(&unknown);
+        assert_eq!(state.sound_theme, Some(SoundTheme::Horn));
+        assert_eq!(state.last_notification, Some(unknown));
+        assert_eq!(state.motion, motion);
+    }
+}
@@ -0,0 +1,48 @@
+//! Links over which the locomotive protocol can be carried.
+
+mod ble;
+mod mock;
+
+use std::pin::Pin;
+
+use futures::Stream;
+
+pub use self::{ble::BtleplugTransport, mock::MockTransport};
+use crate::{BrioError, Result};
+
+/// Stream of raw values notified by the device, one item per notification.
+pub type NotificationStream = Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>;
+
+/// Backend carrying frames between the library and a locomotive.
+///
+/// Frames handed to and returned by a transport are complete, including the
+/// `0xaa` sync byte, the length and the checksum.
+pub trait Transport: Send + Sync + 'static {
+    /// Write a frame to the command characteristic of the device.
+    fn write_frame(
+        &self,
+        frame: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Subscribe to the values notified by the device.
+    fn notifications(
+        &self,
+    ) -> impl Future<Output = Result<NotificationStream>> + Send;
+
+    /// Whether the link with the device is up.
+    fn is_connected(&self) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Restore the link with the same device after it went down.
+    ///
+    /// Once reconnected, [`Transport::notifications`] has to be called again.
+    /// Transports unable to reconnect keep the default implementation, which
+    /// fails with [`BrioError::Disconnected`].
+    fn reconnect(&self) -> impl Future<Output = Result<()>> + Send {
+        async { Err(BrioError::Disconnected) }
+    }
+
+    /// Stop listening to notifications and close the link.
+    fn disconnect(&self) -> impl Future<Output = Result<()>> + Send {
+        async { Ok(()) }
+    }
+}
@@ -0,0 +1,163 @@
+use std::{sync::Mutex, time::Duration};
+
+use btleplug::{
+    api::{CharPropFlags, Characteristic, Peripheral as _, WriteType},
+    platform::{Adapter, Peripheral},
+};
+use futures::stream::StreamExt;
+use tokio::sync::watch;
+
+use super::{NotificationStream, Transport};
+use crate::{
+    BrioError, ConnectionState, DEFAULT_SCAN_TIMEOUT, Result, brio_uuid,
+    discovery,
+};
+
+/// Transport over Bluetooth Low Energy, backed by `btleplug`.
+pub struct BtleplugTransport {
+    central: Adapter,
+    peripheral: Peripheral,
+    /// Refreshed on reconnection, the services being discovered again.
+    cmd_char: Mutex<Characteristic>,
+    /// Time allowed to find the peripheral again when reconnecting.
+    scan_timeout: Duration,
+}
+
+impl BtleplugTransport {
+    /// Connect to the peripheral, discover its services and subscribe to its
+    /// notification characteristic.
+    ///
+    /// `central` is the adapter the peripheral was found with, used to scan
+    /// for it again when reconnecting, for up to [`DEFAULT_SCAN_TIMEOUT`].
+    pub async fn connect(
+        central: &Adapter,
+        peripheral: Peripheral,
+    ) -> Result<Self> {
+        let connection_state = watch::Sender::new(ConnectionState::Connecting);
+        Self::connect_reporting(
+            central,
+            peripheral,
+            DEFAULT_SCAN_TIMEOUT,
+            &connection_state,
+        )
+        .await
+    }
+
+    /// Same as [`Self::connect`], scanning for up to `scan_timeout` when
+    /// reconnecting and reporting the progress on `connection_state`.
+    pub(crate) async fn connect_reporting(
+        central: &Adapter,
+        peripheral: Peripheral,
+        scan_timeout: Duration,
+        connection_state: &watch::Sender<ConnectionState>,
+    ) -> Result<Self> {
+        let cmd_char = setup(&peripheral, connection_state).await?;
+
+        Ok(Self {
+            central: central.clone(),
+            peripheral,
+            cmd_char: Mutex::new(cmd_char),
+            scan_timeout,
+        })
+    }
+
+    pub fn peripheral(&self) -> &Peripheral {
+        &self.peripheral
+    }
+}
+
+/// Connect to the peripheral and subscribe to its notifications, returning
+/// the command characteristic.
+async fn setup(
+    peripheral: &Peripheral,
+    connection_state: &watch::Sender<ConnectionState>,
+) -> Result<Characteristic> {
+    let control_point_uuid = brio_uuid(2);
+    let notification_uuid = brio_uuid(3);
+
+    connection_state.send_replace(ConnectionState::Connecting);
+    peripheral.connect().await?;
+    connection_state.send_replace(ConnectionState::DiscoveringServices);
+    peripheral.discover_services().await?;
+
+    let mut cmd_char: Option<Characteristic> = None;
+
+    for characteristic in peripheral.characteristics() {
+        log::debug!("Characteristic {}", characteristic.uuid);
+        if characteristic.uuid == control_point_uuid {
+            log::debug!("   Control point");
+            cmd_char = Some(characteristic);
+        } else if characteristic.uuid == notification_uuid {
+            log::debug!("   Notification");
+            if !characteristic.properties.contains(CharPropFlags::NOTIFY) {
+                // Unexpected non-notify characteristic.
+                return Err(BrioError::CharacteristicMissing(
+                    notification_uuid,
+                ));
+            }
+            peripheral.subscribe(&characteristic).await?;
+        } else {
+            continue;
+        }
+    }
+
+    cmd_char.ok_or(BrioError::CharacteristicMissing(control_point_uuid))
+}
+
+impl Transport for BtleplugTransport {
+    async fn write_frame(&self, frame: &[u8]) -> Result<()> {
+        let cmd_char = self.cmd_char.lock().unwrap().clone();
+        self.peripheral
+            .write(&cmd_char, frame, WriteType::WithoutResponse)
+            .await?;
+        Ok(())
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream> {
+        let notification_uuid = brio_uuid(3);
+        let stream = self.peripheral.notifications().await?;
+
+        Ok(Box::pin(stream.filter_map(move |data| async move {
+            (data.uuid == notification_uuid).then_some(data.value)
+        })))
+    }
+
+    async fn is_connected(&self) -> Result<bool> {
+        Ok(self.peripheral.is_connected().await?)
+    }
+
+    async fn reconnect(&self) -> Result<()> {
+        // Wait for the locomotive to advertise again before connecting.
+        let address = self.peripheral.address();
+        discovery::scan_for(&self.central, self.scan_timeout, |train| {
+            train.address == address
+        })
+        .await?;
+
+        // The supervisor reports the whole reconnection as one state.
+        let cmd_char = setup(
+            &self.peripheral,
+            &watch::Sender::new(ConnectionState::Reconnecting),
+        )
+        .await?;
+        *self.cmd_char.lock().unwrap() = cmd_char;
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        let notification_uuid = brio_uuid(3);
+
+        // Disconnect even when unsubscribing fails, reporting the first error.
+        let mut result = Ok(());
+        if let Some(characteristic) = self
+            .peripheral
+            .characteristics()
+            .into_iter()
+            .find(|characteristic| characteristic.uuid == notification_uuid)
+        {
+            result = self.peripheral.unsubscribe(&characteristic).await;
+        }
+        result = result.and(self.peripheral.disconnect().await);
+        Ok(result?)
+    }
+}
@@ -0,0 +1,210 @@
+use std::sync::{Arc, Mutex};
+
+use futures::channel::mpsc::{UnboundedSender, unbounded};
+
+use super::{NotificationStream, Transport};
+use crate::{BrioError, Result};
+
+/// In-memory transport for tests.
+///
+/// Every written frame is recorded, and notification frames can be injected
+/// either directly or as scripted responses to a given written frame. Clones
+/// share the same state, so a test can keep a clone to inspect what the
+/// library sent.
+#[derive(Clone)]
+pub struct MockTransport {
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    connected: bool,
+    reachable: bool,
+    written: Vec<Vec<u8>>,
+    responses: Vec<(Vec<u8>, Vec<Vec<u8>>)>,
+    subscribers: Vec<UnboundedSender<Vec<u8>>>,
+}
+
+impl Inner {
+    fn notify(&mut self, frame: &[u8]) {
+        self.subscribers.retain(|subscriber| {
+            subscriber.unbounded_send(frame.to_vec()).is_ok()
+        });
+    }
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                connected: true,
+                reachable: true,
+                written: Vec::new(),
+                responses: Vec::new(),
+                subscribers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Frames written so far, oldest first.
+    pub fn written_frames(&self) -> Vec<Vec<u8>> {
+        self.inner.lock().unwrap().written.clone()
+    }
+
+    /// Frames written so far, clearing the record.
+    pub fn take_written_frames(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.inner.lock().unwrap().written)
+    }
+
+    /// Deliver a notification frame to every subscriber.
+    pub fn inject(&self, frame: impl AsRef<[u8]>) {
+        self.inner.lock().unwrap().notify(frame.as_ref());
+    }
+
+    /// Notify `responses` each time `frame` is written.
+    pub fn respond_to(
+        &self,
+        frame: impl Into<Vec<u8>>,
+        responses: impl IntoIterator<Item = Vec<u8>>,
+    ) {
+        self.inner
+            .lock()
+            .unwrap()
+            .responses
+            .push((frame.into(), responses.into_iter().collect()));
+    }
+
+    /// Simulate the device going in or out of range.
+    ///
+    /// [`Transport::reconnect`] fails while the device is out of range.
+    pub fn set_reachable(&self, reachable: bool) {
+        self.inner.lock().unwrap().reachable = reachable;
+    }
+
+    /// Simulate the link going up or down.
+    ///
+    /// Going down ends every notification stream and makes writes fail.
+    pub fn set_connected(&self, connected: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.connected = connected;
+        if !connected {
+            inner.subscribers.clear();
+        }
+    }
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transport for MockTransport {
+    async fn write_frame(&self, frame: &[u8]) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.connected {
+            return Err(BrioError::Disconnected);
+        }
+        inner.written.push(frame.to_vec());
+
+        let responses: Vec<Vec<u8>> = inner
+            .responses
+            .iter()
+            .filter(|(request, _)| request == frame)
+            .flat_map(|(_, responses)| responses.clone())
+            .collect();
+        for response in responses {
+            inner.notify(&response);
+        }
+        Ok(())
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.connected {
+            return Err(BrioError::Disconnected);
+        }
+        let (sender, receiver) = unbounded();
+        inner.subscribers.push(sender);
+        Ok(Box::pin(receiver))
+    }
+
+    async fn is_connected(&self) -> Result<bool> {
+        Ok(self.inner.lock().unwrap().connected)
+    }
+
+    async fn reconnect(&self) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.reachable {
+            return Err(BrioError::DeviceNotFound);
+        }
+        inner.connected = true;
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        self.set_connected(false);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::{BrioSmartTech, Color, SoundTheme};
+
+    #[tokio::test]
+    async fn test_command_sequence() {
+        let mock = MockTransport::new();
+        let train = BrioSmartTech::with_transport(mock.clone()).await.unwrap();
+
+        train.forward(3).await.unwrap();
+        train.backward(7).await.unwrap();
+        train.set_color(Color::Blue, 15).await.unwrap();
+        train.set_sound_theme(SoundTheme::Spaceship).await.unwrap();
+        train.stop().await.unwrap();
+        train.set_speed(0x12).await.unwrap();
+
+        assert_eq!(
+            mock.take_written_frames(),
+            vec![
+                vec![0xaa, 0x02, 0x01, 0x04, 0xf9],
+                vec![0xaa, 0x02, 0x01, 0x18, 0xe5],
+                vec![0xaa, 0x02, 0x02, 0x6f, 0x8d],
+                vec![0xaa, 0x03, 0x56, 0xaa, 0xf3, 0x0a],
+                vec![0xaa, 0x02, 0x01, 0x00, 0xfd],
+                vec![0xaa, 0x02, 0x01, 0x12, 0xeb],
+            ]
+        );
+        assert!(mock.written_frames().is_empty());
+
+        // Invalid arguments never reach the transport.
+        assert!(train.forward(8).await.is_err());
+        for speed in [0x09, 0x01, 0x11] {
+            assert!(train.set_speed(speed).await.is_err(), "{speed:#04x}");
+        }
+        assert!(mock.written_frames().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_notifications() {
+        let mock = MockTransport::new();
+        let mut notifications = mock.notifications().await.unwrap();
+
+        mock.respond_to(vec![0x01], vec![vec![0x02], vec![0x03]]);
+        mock.inject([0x00]);
+        mock.write_frame(&[0x01]).await.unwrap();
+
+        assert_eq!(notifications.next().await, Some(vec![0x00]));
+        assert_eq!(notifications.next().await, Some(vec![0x02]));
+        assert_eq!(notifications.next().await, Some(vec![0x03]));
+
+        mock.set_connected(false);
+        assert_eq!(notifications.next().await, None);
+        assert!(matches!(
+            mock.write_frame(&[0x01]).await,
+            Err(BrioError::Disconnected)
+        ));
+    }
+}